@@ -4,6 +4,7 @@ var filesToCache = [
   './index.html',
   './poe_planner_egui.js',
   './poe_planner_egui_bg.wasm',
+  './data.json',
 ];
 
 /* Start the service worker and cache all of the app's content */
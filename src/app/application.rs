@@ -1,68 +1,158 @@
-use crate::{tree, TemplateApp};
+use crate::{build::Build, cluster, tree, TemplateApp};
 
-use super::{model::View, utility::image_as_texture};
+use super::{model::View, utility::sheet_texture};
 
 impl TemplateApp {
-    /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    /// Called once before the first frame. The first tree is the one new builds start on.
+    pub fn new(cc: &eframe::CreationContext<'_>, trees: Vec<tree::TreeExport>) -> Self {
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
-        let group_bg_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://group-background-3.png".into(),
-            include_bytes!("../../resources/ggg_assets/group-background-3.png"),
-        );
+        let mut trees = trees.into_iter();
+        let tree = trees.next().unwrap_or_default();
+        let mut app = TemplateApp {
+            lines: tree.generate_lines(),
+            tree,
+            ..Default::default()
+        };
+        for tree in trees {
+            app.register_tree(&cc.egui_ctx, tree);
+        }
+        app.load_textures(&cc.egui_ctx);
+        app.rebuild_hit_grid();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use crate::timeless::{TimelessTables, TIMELESS_DIR};
+            let (tables, errors) = TimelessTables::load_dir(TIMELESS_DIR);
+            for e in errors {
+                log::warn!("{e}");
+            }
+            app.timeless = tables;
+        }
+        app.build.tree_version.clone_from(&app.tree.tree);
 
-        let bg_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://background-3.png".into(),
-            include_bytes!("../../resources/ggg_assets/background-3.png"),
-        );
+        if let Some(storage) = cc.storage {
+            app.restore(&cc.egui_ctx, storage);
+        }
 
-        let skills_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://skills-3.png".into(),
-            include_bytes!("../../resources/ggg_assets/skills-3.png"),
-        );
+        app
+    }
 
-        let frame_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://frame-3.png".into(),
-            include_bytes!("../../resources/ggg_assets/frame-3.png"),
+    /// Points every texture at the active tree's sprite sheets.
+    pub fn load_textures(&mut self, ctx: &egui::Context) {
+        let sprites = &self.tree.sprites;
+        self.group_bg_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.group_background.sprites,
+            "group-background-3.png",
         );
-
-        let inactive_skills_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://skills-disabled-3.jpg".into(),
-            include_bytes!("../../resources/ggg_assets/skills-disabled-3.jpg"),
+        self.bg_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.background.sprites,
+            "background-3.png",
         );
-
-        let mastery_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://mastery-disabled-3.png".into(),
-            include_bytes!("../../resources/ggg_assets/mastery-disabled-3.png"),
+        self.skills_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.normal_active.sprites,
+            "skills-3.jpg",
         );
-
-        let ascendancy_tex = image_as_texture(
-            &cc.egui_ctx,
-            "bytes://ascendancy-background-2.jpg".into(),
-            include_bytes!("../../resources/ggg_assets/ascendancy-background-2.jpg"),
+        self.frame_tex = sheet_texture(ctx, &self.tree, &sprites.frame.sprites, "frame-3.png");
+        self.inactive_skills_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.normal_inactive.sprites,
+            "skills-disabled-3.jpg",
+        );
+        self.skills_worse_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.normal_active.worse_sprites,
+            "skills-2.jpg",
+        );
+        self.inactive_skills_worse_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.normal_inactive.worse_sprites,
+            "skills-disabled-2.jpg",
+        );
+        self.mastery_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.mastery_inactive.sprites,
+            "mastery-disabled-3.png",
         );
+        self.mastery_connected_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.mastery_connected.sprites,
+            "mastery-connected-3.png",
+        );
+        self.mastery_selected_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.mastery_active_selected.sprites,
+            "mastery-active-selected-3.png",
+        );
+        self.jewel_tex = sheet_texture(ctx, &self.tree, &sprites.jewel.sprites, "jewel-3.png");
+        self.jewel_radius_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.jewel_radius.sprites,
+            "jewel-radius.png",
+        );
+        self.ascendancy_tex = sheet_texture(
+            ctx,
+            &self.tree,
+            &sprites.ascendancy_background.worse_sprites,
+            "ascendancy-background-2.jpg",
+        );
+    }
 
-        let tree = tree::TreeExport::new().unwrap();
-        let lines = tree.generate_lines();
-
-        TemplateApp {
-            group_bg_tex,
-            bg_tex,
-            skills_tex,
-            frame_tex,
-            inactive_skills_tex,
-            mastery_tex,
-            ascendancy_tex,
-            lines,
-            ..Default::default()
+    fn restore(&mut self, ctx: &egui::Context, storage: &dyn eframe::Storage) {
+        if let Some(class) = eframe::get_value(storage, "selected_class") {
+            self.selected_class = class;
+        }
+        let Some(mut build) = eframe::get_value::<Build>(storage, "build") else {
+            return;
+        };
+        if build.tree_version != self.tree.tree && !self.switch_tree(ctx, &build.tree_version) {
+            log::warn!(
+                "Saved build targets tree {:?} which isn't loaded, using {:?}",
+                build.tree_version,
+                self.tree.tree
+            );
+            build.tree_version.clone_from(&self.tree.tree);
         }
+        let starts = self.tree.graph.start_nodes(
+            self.selected_class.id() as usize,
+            self.tree
+                .ascendancy(self.selected_class.id() as usize, build.ascendancy)
+                .map(|a| a.id.as_str()),
+        );
+        cluster::generate(&mut self.tree.graph, &build.jewels);
+        match build.validate(&self.tree.graph, &starts) {
+            Ok(()) => self.build = build,
+            Err(e) => log::warn!("Discarding saved build: {e}"),
+        }
+        self.regenerate_clusters();
+    }
+
+    fn frame_stats(&self, ui: &mut egui::Ui) {
+        let average = self.frame_times.average().unwrap_or_default() * 1000.0;
+        let worst = self
+            .frame_times
+            .values()
+            .fold(0.0_f32, |worst, time| worst.max(time))
+            * 1000.0;
+        ui.label(format!("Frame {average:.2} ms (worst {worst:.2} ms)"))
+            .on_hover_text("CPU time per frame over the last second");
+        ui.separator();
+        ui.label(format!(
+            "Drawing {} nodes, {} lines at {:.2}x",
+            self.render_stats.nodes, self.render_stats.lines, self.camera.zoom
+        ));
     }
 }
 
@@ -90,8 +180,7 @@ impl eframe::App for TemplateApp {
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.set_min_height(10.0);
-                ui.label("Bottom Panel");
-                let _ = ui.button("Buttom Button");
+                self.frame_stats(ui);
             });
         });
 
@@ -102,6 +191,15 @@ impl eframe::App for TemplateApp {
                 View::NoteEdit => self.edit_notes(ui, ctx),
             };
         });
+
+        self.migration_report_window(ctx);
+        self.mastery_window(ctx);
+        self.jewel_window(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, "selected_class", &self.selected_class);
+        eframe::set_value(storage, "build", &self.build);
     }
 
     fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
@@ -0,0 +1,347 @@
+use egui::{Color32, Context, Ui};
+
+use crate::{
+    build::Build,
+    cluster::{self, ClusterJewel},
+    graph::TreeGraph,
+    jewel::{self, Band, Jewel, JewelBase, JewelRadius},
+    pathing,
+    timeless::{self, TimelessKind, TimelessSeed, TimelessTables},
+    TemplateApp,
+};
+
+use super::model::JewelEditor;
+
+impl TemplateApp {
+    pub fn open_jewel_editor(&mut self, socket: usize) {
+        let jewel = self
+            .build
+            .jewels
+            .get(&socket)
+            .cloned()
+            .unwrap_or_else(|| Jewel::new(JewelBase::Crimson));
+        let seed = TimelessSeed::from_mods(&jewel.mods).unwrap_or_else(|| {
+            let kind = TimelessKind::GloriousVanity;
+            TimelessSeed {
+                kind,
+                seed: kind.seeds().0,
+                conqueror: kind.conquerors()[0].to_string(),
+            }
+        });
+        self.jewel_editor = Some(JewelEditor {
+            socket,
+            name: jewel.name,
+            base: jewel.base,
+            unique: jewel.unique,
+            mods: jewel.mods.join("\n"),
+            radius: jewel.radius,
+            timeless_kind: seed.kind,
+            seed: seed.seed,
+            conqueror: seed.conqueror,
+            seed_query: String::new(),
+            allocated_only: true,
+            seed_results: vec![],
+        });
+    }
+
+    pub fn jewel_window(&mut self, ctx: &Context) {
+        let Some(editor) = &mut self.jewel_editor else {
+            return;
+        };
+        let socket = editor.socket;
+        let socketed = self.build.jewels.contains_key(&socket);
+
+        let mut open = true;
+        let mut save = false;
+        let mut remove = false;
+        let mut refund = false;
+        egui::Window::new("Jewel Socket")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("jewel_editor")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut editor.name);
+                        ui.end_row();
+
+                        ui.label("Base");
+                        egui::ComboBox::from_id_source("jewel_base")
+                            .selected_text(editor.base.base_name())
+                            .show_ui(ui, |ui| {
+                                for base in JewelBase::ALL {
+                                    ui.selectable_value(&mut editor.base, base, base.base_name());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Radius");
+                        egui::ComboBox::from_id_source("jewel_radius")
+                            .selected_text(editor.radius.map_or("None", |r| r.name()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut editor.radius, None, "None");
+                                for radius in JewelRadius::ALL {
+                                    ui.selectable_value(
+                                        &mut editor.radius,
+                                        Some(radius),
+                                        radius.name(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("");
+                        ui.checkbox(&mut editor.unique, "Unique");
+                        ui.end_row();
+                    });
+                ui.label("Mods, one per line");
+                ui.add(
+                    egui::TextEdit::multiline(&mut editor.mods)
+                        .desired_rows(6)
+                        .hint_text("+12% to Fire Resistance"),
+                );
+                if editor.base == JewelBase::Timeless {
+                    ui.separator();
+                    timeless_section(ui, editor, &self.timeless, &self.tree.graph, &self.build);
+                }
+                if editor.base.cluster_size().is_some() {
+                    ui.separator();
+                    cluster_section(ui, editor, &self.tree.graph);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                    if socketed {
+                        remove = ui.button("Remove jewel").clicked();
+                    }
+                    refund = ui
+                        .button("Refund socket")
+                        .on_hover_text("The jewel stays in the socket but stops counting")
+                        .clicked();
+                });
+            });
+
+        if save {
+            self.build.jewels.insert(socket, edited_jewel(editor));
+        }
+        if remove {
+            self.build.jewels.remove(&socket);
+        }
+        if save || remove {
+            self.regenerate_clusters();
+        }
+        if refund {
+            let starts = self.start_nodes();
+            let orphaned =
+                pathing::orphaned_by_removing(&self.tree.graph, &self.build, &starts, socket);
+            self.build.refund(&orphaned);
+        }
+        if !open || save || remove || refund {
+            self.jewel_editor = None;
+        }
+    }
+
+    /// Rebuilds the cluster jewel subtrees from the build's jewels. Allocations on nodes that
+    /// are gone, or that the new layout cuts off, are refunded.
+    pub fn regenerate_clusters(&mut self) {
+        cluster::generate(&mut self.tree.graph, &self.build.jewels);
+        let graph = &self.tree.graph;
+        let missing = self
+            .build
+            .allocated
+            .iter()
+            .copied()
+            .filter(|&skill| graph.node(skill).is_none())
+            .collect();
+        self.build.refund(&missing);
+        if !missing.is_empty() {
+            self.refund_disconnected();
+        }
+        self.lines = self.tree.generate_lines();
+        self.rebuild_hit_grid();
+        self.refresh_search();
+    }
+}
+
+fn edited_jewel(editor: &JewelEditor) -> Jewel {
+    Jewel {
+        name: editor.name.trim().to_string(),
+        base: editor.base,
+        unique: editor.unique,
+        mods: editor
+            .mods
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        radius: editor.radius,
+    }
+}
+
+/// What the cluster jewel will add, read back from its mods, and whether the socket takes it.
+fn cluster_section(ui: &mut Ui, editor: &JewelEditor, graph: &TreeGraph) {
+    let Some(cluster) = ClusterJewel::from_jewel(&edited_jewel(editor)) else {
+        return;
+    };
+    let expansion = graph
+        .node(editor.socket)
+        .and_then(|node| node.expansion_jewel.as_ref());
+    if !cluster.fits(expansion) {
+        ui.colored_label(
+            Color32::LIGHT_RED,
+            format!("A {} Cluster Jewel doesn't fit this socket", cluster.size),
+        );
+    }
+    let (min, max) = cluster.size.passives();
+    ui.label(format!(
+        "Adds {} passives ({min}-{max}), {} socket(s)",
+        cluster.passives, cluster.sockets
+    ));
+    if cluster.notables.is_empty() {
+        ui.weak("No notables, add \"1 Added Passive Skill is <name>\" lines");
+    } else {
+        ui.label(format!("Notables: {}", cluster.notables.join(", ")));
+    }
+    for stat in &cluster.small_stats {
+        ui.label(format!("Small passives: {stat}"));
+    }
+}
+
+/// Seed, conqueror and seed search for timeless jewels. Picking a seed writes its mod into the
+/// editor, the same line the game shows on the jewel.
+fn timeless_section(
+    ui: &mut Ui,
+    editor: &mut JewelEditor,
+    tables: &TimelessTables,
+    graph: &TreeGraph,
+    build: &Build,
+) {
+    let kind = editor.timeless_kind;
+    let (min, max) = kind.seeds();
+    let mut set_seed = false;
+    egui::Grid::new("timeless_seed")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Jewel");
+            egui::ComboBox::from_id_source("timeless_kind")
+                .selected_text(kind.name())
+                .show_ui(ui, |ui| {
+                    for option in TimelessKind::ALL {
+                        ui.selectable_value(&mut editor.timeless_kind, option, option.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Seed");
+            let seed = egui::DragValue::new(&mut editor.seed)
+                .range(min..=max)
+                .speed(kind.seed_step());
+            if ui.add(seed).changed() {
+                editor.seed = kind.snap_seed(editor.seed);
+            }
+            ui.end_row();
+
+            ui.label("Conqueror");
+            egui::ComboBox::from_id_source("timeless_conqueror")
+                .selected_text(editor.conqueror.as_str())
+                .show_ui(ui, |ui| {
+                    for conqueror in kind.conquerors() {
+                        ui.selectable_value(
+                            &mut editor.conqueror,
+                            conqueror.to_string(),
+                            conqueror,
+                        );
+                    }
+                });
+            ui.end_row();
+        });
+    if editor.timeless_kind != kind {
+        let kind = editor.timeless_kind;
+        editor.seed = kind.seeds().0;
+        editor.conqueror = kind.conquerors()[0].to_string();
+        editor.seed_results.clear();
+    }
+    set_seed |= ui.button("Set seed mod").clicked();
+
+    let kind = editor.timeless_kind;
+    let Some(table) = tables.get(kind) else {
+        ui.weak(format!(
+            "No {}.json lookup table loaded, the jewel won't change any passives",
+            kind.file_stem()
+        ));
+        apply_seed(editor, set_seed);
+        return;
+    };
+
+    ui.label("Find seeds with");
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut editor.seed_query)
+                .hint_text("attack speed, maximum life"),
+        );
+        ui.checkbox(&mut editor.allocated_only, "Allocated only");
+    });
+    if ui.button("Search").clicked() {
+        let band = Band {
+            inner: 0.0,
+            outer: editor.radius.unwrap_or(JewelRadius::Large).outer(),
+        };
+        let nodes: Vec<usize> = graph
+            .index_of(editor.socket)
+            .map(|index| jewel::nodes_in_band(graph, graph.positions[index], band))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|&index| !editor.allocated_only || build.is_allocated(graph.skill(index)))
+            .collect();
+        let wanted: Vec<String> = editor
+            .seed_query
+            .split(',')
+            .map(str::trim)
+            .filter(|stat| !stat.is_empty())
+            .map(str::to_string)
+            .collect();
+        editor.seed_results =
+            timeless::search_seeds(table, graph, &nodes, &editor.conqueror, &wanted);
+    }
+    egui::ScrollArea::vertical()
+        .max_height(160.0)
+        .show(ui, |ui| {
+            for result in editor.seed_results.iter().take(50) {
+                ui.horizontal(|ui| {
+                    let counts: Vec<String> = result
+                        .counts
+                        .iter()
+                        .map(|count| count.to_string())
+                        .collect();
+                    ui.label(format!("Seed {}: {}", result.seed, counts.join(" / ")));
+                    if ui.small_button("Use").clicked() {
+                        editor.seed = result.seed;
+                        set_seed = true;
+                    }
+                });
+            }
+        });
+    apply_seed(editor, set_seed);
+}
+
+fn apply_seed(editor: &mut JewelEditor, apply: bool) {
+    if !apply {
+        return;
+    }
+    let mut jewel = edited_jewel(editor);
+    TimelessSeed {
+        kind: editor.timeless_kind,
+        seed: editor.seed,
+        conqueror: editor.conqueror.clone(),
+    }
+    .apply_to(&mut jewel);
+    editor.mods = jewel.mods.join("\n");
+    editor.radius.get_or_insert(JewelRadius::Large);
+    // Timeless jewels are uniques named after their kind.
+    editor.unique = true;
+    editor.name = editor.timeless_kind.name().to_string();
+}
@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use egui::{Context, SelectableLabel};
+
+use crate::TemplateApp;
+
+impl TemplateApp {
+    pub fn mastery_window(&mut self, ctx: &Context) {
+        let Some(mastery) = self.mastery_popup else {
+            return;
+        };
+        let graph = &self.tree.graph;
+        let Some(node) = graph.node(mastery) else {
+            self.mastery_popup = None;
+            return;
+        };
+
+        let mut open = true;
+        let mut chosen = None;
+        let mut refund = false;
+        egui::Window::new(node.name.as_deref().unwrap_or("Mastery"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let current = self.build.mastery_effects.get(&mastery);
+                for effect in &node.mastery_effects {
+                    let taken = self
+                        .build
+                        .is_effect_taken_elsewhere(graph, mastery, effect.effect);
+                    let label = SelectableLabel::new(
+                        current == Some(&effect.effect),
+                        effect.stats.join("\n"),
+                    );
+                    let response = ui
+                        .add_enabled(!taken, label)
+                        .on_disabled_hover_text("Already taken on another mastery of this kind");
+                    if response.clicked() {
+                        chosen = Some(effect.effect);
+                    }
+                }
+                if self.build.is_allocated(mastery) {
+                    ui.separator();
+                    refund = ui.button("Refund").clicked();
+                }
+            });
+
+        if let Some(effect) = chosen {
+            self.build.choose_mastery_effect(mastery, effect);
+        }
+        if refund {
+            self.build.refund(&HashSet::from([mastery]));
+        }
+        if !open || chosen.is_some() || refund {
+            self.mastery_popup = None;
+        }
+    }
+}
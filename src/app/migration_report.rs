@@ -0,0 +1,92 @@
+use egui::{Context, Ui};
+
+use crate::{migration, pathing, TemplateApp};
+
+impl TemplateApp {
+    pub fn migration_report_window(&mut self, ctx: &Context) {
+        let Some(report) = &self.migration_report else {
+            return;
+        };
+        let title = format!(
+            "Migrated from {} to {}",
+            report.from_version, report.to_version
+        );
+        let mut reconnect = false;
+        let mut close = false;
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let graph = &self.tree.graph;
+                let name = |skill: usize| {
+                    graph
+                        .node(skill)
+                        .and_then(|node| node.name.clone())
+                        .unwrap_or_else(|| skill.to_string())
+                };
+
+                section(ui, "Removed", &report.removed, |(skill, name)| {
+                    format!("{name} ({skill})")
+                });
+                section(ui, "Renamed", &report.renamed, |(_, old, new)| {
+                    format!("{old} → {new}")
+                });
+                section(ui, "Matched by name", &report.remapped, |(old, new)| {
+                    format!("{} ({old} → {new})", name(*new))
+                });
+                section(ui, "Disconnected", &report.disconnected, |&skill| {
+                    name(skill)
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if !report.disconnected.is_empty() {
+                        reconnect = ui
+                            .button("Reconnect")
+                            .on_hover_text("Allocate the cheapest paths back to the class start")
+                            .clicked();
+                    }
+                    close = ui
+                        .button("Done")
+                        .on_hover_text("Refunds anything still disconnected")
+                        .clicked();
+                });
+            });
+
+        if reconnect {
+            self.reconnect_migrated();
+        }
+        if close {
+            self.refund_disconnected();
+            self.migration_report = None;
+        }
+    }
+
+    fn reconnect_migrated(&mut self) {
+        let starts = self.start_nodes();
+        let graph = &self.tree.graph;
+        let added = migration::reconnect(graph, &mut self.build, &starts);
+        log::info!("Reconnected migrated build using {} point(s)", added.len());
+
+        let connected = pathing::connected_to_start(graph, &self.build.allocated, &starts);
+        if let Some(report) = &mut self.migration_report {
+            report
+                .disconnected
+                .retain(|skill| !connected.contains(skill));
+        }
+    }
+}
+
+fn section<T>(ui: &mut Ui, heading: &str, entries: &[T], label: impl Fn(&T) -> String) {
+    if entries.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new(format!("{heading} ({})", entries.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            for entry in entries {
+                ui.label(label(entry));
+            }
+        });
+}
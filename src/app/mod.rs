@@ -1,9 +1,15 @@
 pub mod application;
+pub mod jewel;
+pub mod mastery;
+pub mod migration_report;
 pub mod model;
 pub mod notes;
 pub mod passive_tree;
+pub mod search_bar;
+pub mod sharing;
 pub mod sidebar;
 pub mod top_panel;
 pub mod utility;
+pub mod versions;
 
 pub use model::TemplateApp;
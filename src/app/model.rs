@@ -1,8 +1,23 @@
-use egui::{util::History, Pos2, TextureId};
+use std::collections::BTreeMap;
 
-use crate::{camera::Camera, tree};
+use egui::{util::History, TextureId};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    build::Build,
+    camera::Camera,
+    graph::NodeIndex,
+    jewel::{JewelBase, JewelRadius},
+    migration::MigrationReport,
+    pob,
+    search::SearchMode,
+    spatial::NodeGrid,
+    stats::StatCache,
+    timeless::{SeedMatch, TimelessKind, TimelessTables},
+    tree,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum CharacterClass {
     Scion,
     Marauder,
@@ -25,6 +40,19 @@ impl CharacterClass {
             Self::Shadow => 6,
         }
     }
+
+    pub fn from_id(id: i32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Scion),
+            1 => Some(Self::Marauder),
+            2 => Some(Self::Ranger),
+            3 => Some(Self::Witch),
+            4 => Some(Self::Duelist),
+            5 => Some(Self::Templar),
+            6 => Some(Self::Shadow),
+            _ => None,
+        }
+    }
 }
 
 pub enum View {
@@ -33,21 +61,88 @@ pub enum View {
     NoteEdit,
 }
 
+#[derive(Default)]
+pub struct NodeSearch {
+    pub query: String,
+    pub mode: SearchMode,
+    pub results: Vec<NodeIndex>,
+    /// Position in `results` of the match being shown.
+    pub current: usize,
+    pub error: Option<String>,
+}
+
+/// The jewel being edited for a socket, applied to the build when saved.
+pub struct JewelEditor {
+    pub socket: usize,
+    pub name: String,
+    pub base: JewelBase,
+    pub unique: bool,
+    /// Mod lines as typed, one per line.
+    pub mods: String,
+    pub radius: Option<JewelRadius>,
+    pub timeless_kind: TimelessKind,
+    pub seed: u32,
+    pub conqueror: String,
+    /// Comma separated stats to look for with the seed search.
+    pub seed_query: String,
+    /// Limit the seed search to passives that are allocated.
+    pub allocated_only: bool,
+    pub seed_results: Vec<SeedMatch>,
+}
+
 pub struct TemplateApp {
+    /// The tree the build is planned on.
     pub tree: tree::TreeExport,
+    /// Every other loaded tree, keyed by `TreeExport::tree`.
+    pub trees: BTreeMap<String, tree::TreeExport>,
     pub group_bg_tex: TextureId,
     pub bg_tex: TextureId,
     pub skills_tex: TextureId,
     pub inactive_skills_tex: TextureId,
+    /// Node icons from the export's smaller sheets, drawn when zoomed out.
+    pub skills_worse_tex: TextureId,
+    pub inactive_skills_worse_tex: TextureId,
     pub frame_tex: TextureId,
     pub mastery_tex: TextureId,
+    pub jewel_tex: TextureId,
+    pub jewel_radius_tex: TextureId,
+    pub mastery_connected_tex: TextureId,
+    pub mastery_selected_tex: TextureId,
     pub ascendancy_tex: TextureId,
     pub frame_times: History<f32>,
-    pub lines: Vec<(Pos2, Pos2)>,
+    /// What the passive tree drew last frame.
+    pub render_stats: RenderStats,
+    pub lines: Vec<tree::Line>,
+    /// Hit areas of the active tree's nodes, rebuilt along with `lines`.
+    pub hit_grid: NodeGrid,
     pub camera: Camera,
+    /// Node the camera should center on next frame, once the canvas size is known.
+    pub focus_request: Option<NodeIndex>,
     pub selected_class: CharacterClass,
+    pub build: Build,
     pub selected_view: View,
     pub notes_buffer: String,
+    pub import_buffer: String,
+    pub import_error: Option<String>,
+    /// What a successful import had to leave out.
+    pub import_notice: Option<String>,
+    /// The last imported Path of Building document, kept for the sections the planner ignores.
+    pub pob_document: Option<pob::PobBuild>,
+    /// Outcome of the last tree switch, shown until the player deals with it.
+    pub migration_report: Option<MigrationReport>,
+    pub search: NodeSearch,
+    /// Mastery whose effect picker is open.
+    pub mastery_popup: Option<usize>,
+    pub jewel_editor: Option<JewelEditor>,
+    /// Timeless jewel lookup tables found on disk.
+    pub timeless: TimelessTables,
+    pub stat_cache: StatCache,
+}
+
+#[derive(Default)]
+pub struct RenderStats {
+    pub nodes: usize,
+    pub lines: usize,
 }
 
 impl Default for TemplateApp {
@@ -55,21 +150,41 @@ impl Default for TemplateApp {
         let max_age: f32 = 1.0;
         let max_len = (max_age * 300.0).round() as usize;
         let camera = Camera::new(0.0, 0.0, 1.0, 400.0, 300.0);
-        let tree = tree::TreeExport::new().unwrap();
         Self {
-            tree,
+            tree: Default::default(),
+            trees: Default::default(),
             group_bg_tex: TextureId::User(999),
             bg_tex: TextureId::User(999),
             skills_tex: TextureId::User(999),
             frame_tex: TextureId::User(999),
             inactive_skills_tex: TextureId::User(999),
+            skills_worse_tex: TextureId::User(999),
+            inactive_skills_worse_tex: TextureId::User(999),
             ascendancy_tex: TextureId::User(999),
             mastery_tex: Default::default(),
+            jewel_tex: Default::default(),
+            jewel_radius_tex: Default::default(),
+            mastery_connected_tex: Default::default(),
+            mastery_selected_tex: Default::default(),
             lines: Default::default(),
+            hit_grid: Default::default(),
+            focus_request: None,
             selected_class: CharacterClass::Scion,
+            build: Default::default(),
             frame_times: History::new(0..max_len, max_age),
+            render_stats: Default::default(),
             selected_view: View::PassiveTree,
             notes_buffer: Default::default(),
+            import_buffer: Default::default(),
+            import_error: None,
+            import_notice: None,
+            pob_document: None,
+            migration_report: None,
+            search: Default::default(),
+            mastery_popup: None,
+            jewel_editor: None,
+            timeless: Default::default(),
+            stat_cache: Default::default(),
             camera,
         }
     }
@@ -1,21 +1,99 @@
-use egui::{pos2, Color32, Context, Rect, Stroke, Ui};
+use std::collections::HashSet;
+
+use egui::{pos2, Color32, Context, Pos2, Rect, RichText, Stroke, Ui, Vec2};
 
 use crate::{
-    tree::{FrameType, CLASS_ART},
+    jewel::Jewel,
+    pathing,
+    spatial::NodeGrid,
+    timeless::{self, Replacement},
+    tree::{FrameType, Node, CLASS_ART},
     TemplateApp,
 };
 
-use super::utility::draw_asset;
+use super::{
+    model::RenderStats,
+    utility::{draw_asset, draw_asset_sized, draw_asset_tinted, sprite_extent},
+};
+
+const LINE_INACTIVE: Color32 = Color32::from_gray(120);
+const LINE_ACTIVE: Color32 = Color32::from_rgb(230, 200, 125);
+const LINE_PREVIEW: Color32 = Color32::from_rgb(140, 190, 240);
+const REFUND_TINT: Color32 = Color32::from_rgb(255, 110, 110);
+const DIMMED_TINT: Color32 = Color32::from_gray(90);
+const SEARCH_MATCH: Color32 = Color32::from_rgb(120, 220, 255);
+const SEARCH_CURRENT: Color32 = Color32::from_rgb(255, 255, 120);
+const TOOLTIP_NAME: Color32 = Color32::from_rgb(230, 200, 125);
+const TOOLTIP_STAT: Color32 = Color32::from_rgb(136, 136, 255);
+const TOOLTIP_FLAVOUR: Color32 = Color32::from_rgb(175, 96, 37);
+const RADIUS_EDGE: Color32 = Color32::from_rgb(150, 170, 255);
+/// Ring drawn for jewel radii, from the export's `jewelRadius` sheet.
+const RADIUS_ART: &str = "JewelCircle1";
+/// Below this zoom node icons come from the export's smaller sprite sheets.
+const REDUCED_ZOOM: f32 = 0.25;
+/// Below this zoom frames and group backgrounds are left out, they're too small to make out.
+const OUTLINE_ZOOM: f32 = 0.1;
+/// How far a node's icon and frame reach past its position, in tree units.
+const NODE_EXTENT: f32 = 200.0;
+/// Hit radius for nodes whose sprite isn't in the loaded sheets, in tree units.
+const DEFAULT_HIT_RADIUS: f32 = 50.0;
+
+fn frame_name(frame_type: FrameType, allocated: bool, hovered: bool) -> Option<&'static str> {
+    match frame_type {
+        FrameType::Keystone if allocated => Some("KeystoneFrameAllocated"),
+        FrameType::Keystone if hovered => Some("KeystoneFrameCanAllocate"),
+        FrameType::Keystone => Some("KeystoneFrameUnallocated"),
+        FrameType::Notable if allocated => Some("NotableFrameAllocated"),
+        FrameType::Notable if hovered => Some("NotableFrameCanAllocate"),
+        FrameType::Notable => Some("NotableFrameUnallocated"),
+        FrameType::Normal if allocated => Some("PSSkillFrameActive"),
+        FrameType::Normal if hovered => Some("PSSkillFrameHighlighted"),
+        FrameType::Normal => Some("PSSkillFrame"),
+        FrameType::Jewel if allocated => Some("JewelFrameAllocated"),
+        FrameType::Jewel if hovered => Some("JewelFrameCanAllocate"),
+        FrameType::Jewel => Some("JewelFrameUnallocated"),
+        FrameType::None => None,
+    }
+}
 
 impl TemplateApp {
+    /// Indexes the nodes for hover and clicks, each hit within the frame or icon drawn for it.
+    pub fn rebuild_hit_grid(&mut self) {
+        let sprites = &self.tree.sprites;
+        self.hit_grid = NodeGrid::new(&self.tree.graph, |node| {
+            if node.is_proxy.unwrap_or(false) || node.is_ascendancy_start {
+                return None;
+            }
+            let sprite = if node.is_mastery {
+                sprites
+                    .mastery_inactive
+                    .sprites
+                    .coords
+                    .get(&node.inactive_icon)
+            } else {
+                frame_name(node.frame_type(), false, false)
+                    .and_then(|name| sprites.frame.sprites.coords.get(name))
+            };
+            Some(sprite.map_or(DEFAULT_HIT_RADIUS, |sprite| {
+                sprite_extent(sprite, Pos2::ZERO, false).width() / 2.0
+            }))
+        });
+    }
+
     pub fn passive_tree(&mut self, ui: &mut Ui, ctx: &Context) {
         let available_size = ui.available_size();
-        let (response, painter) = ui.allocate_painter(available_size, egui::Sense::drag());
-        let mut hovered_node = "";
+        let (response, painter) =
+            ui.allocate_painter(available_size, egui::Sense::click_and_drag());
+        let graph = &self.tree.graph;
+        let mut hovered_node = None;
 
         if response.dragged() {
             self.camera.pan(response.drag_delta().to_pos2());
         }
+        if let Some(index) = self.focus_request.take() {
+            self.camera
+                .center_on(graph.positions[index], response.rect.center());
+        }
 
         let zoom_factor = 1.1;
         if response.hovered() {
@@ -30,17 +108,79 @@ impl TemplateApp {
             }
 
             if let Some(hover_pos) = response.hover_pos() {
-                for (id, node) in &self.tree.nodes {
-                    let node_pos = self.tree.node_position(node);
-                    let screen_pos = self.camera.world_to_screen(pos2(node_pos.0, node_pos.1));
-                    let distance = screen_pos.distance(hover_pos);
-                    if distance < 50.0 * self.camera.zoom {
-                        hovered_node = id;
-                    }
-                }
+                hovered_node = self
+                    .hit_grid
+                    .node_at(self.camera.screen_to_world(hover_pos));
             }
         }
 
+        // Only borrows the tree, the build is still changed below.
+        let ascendancy = self
+            .tree
+            .ascendancy(self.selected_class.id() as usize, self.build.ascendancy)
+            .map(|ascendancy| ascendancy.id.as_str());
+        let hovered_skill = hovered_node
+            .map(|index| &graph.nodes[index])
+            .filter(|node| node.is_allocatable())
+            .filter(|node| {
+                node.ascendancy_name.is_none() || node.ascendancy_name.as_deref() == ascendancy
+            })
+            .and_then(|node| node.skill);
+        let starts = graph.start_nodes(self.selected_class.id() as usize, ascendancy);
+        // Allocated sockets open the jewel editor on click, which is also where they're refunded.
+        let hovered_socket = hovered_skill.filter(|&skill| {
+            self.build.is_allocated(skill) && graph.node(skill).is_some_and(|n| n.is_jewel_socket)
+        });
+        let (mut hover_path, refund) = match hovered_skill {
+            _ if hovered_socket.is_some() => (None, HashSet::new()),
+            Some(skill) if self.build.is_allocated(skill) => (
+                None,
+                pathing::orphaned_by_removing(graph, &self.build, &starts, skill),
+            ),
+            Some(skill) => (
+                pathing::shortest_path(graph, &self.build, &starts, skill),
+                HashSet::new(),
+            ),
+            None => (None, HashSet::new()),
+        };
+        let ascendancy_cost = hover_path.iter().flatten().filter(|&&skill| {
+            graph
+                .node(skill)
+                .is_some_and(|node| node.ascendancy_name.is_some())
+        });
+        let over_budget = self.build.ascendancy_points_used(graph) + ascendancy_cost.count()
+            > self.build.progress.ascendancy_points();
+        if over_budget {
+            hover_path = None;
+        }
+
+        let hovered_mastery = hovered_node
+            .map(|index| &graph.nodes[index])
+            .filter(|node| node.is_mastery)
+            .and_then(|node| node.skill)
+            .filter(|&skill| self.build.can_take_mastery(graph, skill));
+
+        let mut edit_jewel = None;
+        if response.clicked() {
+            if hovered_mastery.is_some() {
+                self.mastery_popup = hovered_mastery;
+            } else if hovered_socket.is_some() {
+                edit_jewel = hovered_socket;
+            } else if let Some(path) = &hover_path {
+                self.build.allocate_path(path);
+            } else if !refund.is_empty() {
+                self.build.refund(&refund);
+            }
+        }
+        let preview: HashSet<usize> = hover_path.iter().flatten().copied().collect();
+
+        // Only what's on screen gets drawn, in less detail the further out the camera is.
+        let view = self.camera.visible_world(response.rect);
+        let node_view = view.expand(NODE_EXTENT);
+        let reduced = self.camera.zoom < REDUCED_ZOOM;
+        let outline = self.camera.zoom < OUTLINE_ZOOM;
+        let mut stats = RenderStats::default();
+
         painter.image(
             self.bg_tex,
             Rect::from_min_max(
@@ -54,16 +194,22 @@ impl TemplateApp {
             Color32::from_rgb(255, 255, 255),
         );
 
-        for group in self.tree.groups.values() {
+        for group in graph.groups.iter().filter(|_| !outline) {
             if let Some(bg) = &group.background {
                 let spritesheet = &self.tree.sprites.group_background.sprites;
-                if let Some(sprite) = spritesheet.coords.get(&bg.image) {
+                let position = pos2(group.x, group.y);
+                let is_half = bg.is_half_image.unwrap_or(false);
+                let sprite = spritesheet
+                    .coords
+                    .get(&bg.image)
+                    .filter(|sprite| view.intersects(sprite_extent(sprite, position, is_half)));
+                if let Some(sprite) = sprite {
                     draw_asset(
                         sprite,
                         spritesheet,
                         self.group_bg_tex,
-                        pos2(group.x, group.y),
-                        bg.is_half_image.unwrap_or(false),
+                        position,
+                        is_half,
                         &painter,
                         &self.camera,
                     );
@@ -71,15 +217,25 @@ impl TemplateApp {
             }
         }
 
+        let selected_background = ascendancy.map(|name| format!("Classes{name}"));
         for (start_point, tex_name) in self.tree.get_ascendancy_starts() {
             let spritesheet = &self.tree.sprites.ascendancy_background.worse_sprites;
-            if let Some(sprite) = spritesheet.coords.get(&tex_name) {
-                draw_asset(
+            let tint = if selected_background.as_ref() == Some(&tex_name) {
+                Color32::WHITE
+            } else {
+                DIMMED_TINT
+            };
+            let sprite = spritesheet
+                .coords
+                .get(&tex_name)
+                .filter(|sprite| view.intersects(sprite_extent(sprite, start_point, false)));
+            if let Some(sprite) = sprite {
+                draw_asset_tinted(
                     sprite,
                     spritesheet,
                     self.ascendancy_tex,
                     start_point,
-                    false,
+                    tint,
                     &painter,
                     &self.camera,
                 );
@@ -87,26 +243,108 @@ impl TemplateApp {
         }
 
         for line in &self.lines {
-            let from = self.camera.world_to_screen(line.0);
-            let to = self.camera.world_to_screen(line.1);
+            if !view.intersects(Rect::from_two_pos(line.start, line.end)) {
+                continue;
+            }
+            stats.lines += 1;
+            let from = self.camera.world_to_screen(line.start);
+            let to = self.camera.world_to_screen(line.end);
+            let (from_skill, to_skill) = (graph.skill(line.from), graph.skill(line.to));
+            let from_allocated = self.build.is_allocated(from_skill);
+            let to_allocated = self.build.is_allocated(to_skill);
+            let from_previewed = preview.contains(&from_skill);
+            let to_previewed = preview.contains(&to_skill);
+            let color = if refund.contains(&from_skill) || refund.contains(&to_skill) {
+                if from_allocated && to_allocated {
+                    REFUND_TINT
+                } else {
+                    LINE_INACTIVE
+                }
+            } else if from_allocated && to_allocated {
+                LINE_ACTIVE
+            } else if (from_previewed || from_allocated) && (to_previewed || to_allocated) {
+                LINE_PREVIEW
+            } else {
+                LINE_INACTIVE
+            };
 
-            painter.line_segment(
-                [from, to],
-                Stroke::new(5.0 * self.camera.zoom, Color32::WHITE),
-            );
+            painter.line_segment([from, to], Stroke::new(5.0 * self.camera.zoom, color));
+        }
+
+        let radius_art = &self.tree.sprites.jewel_radius.sprites;
+        let ring = radius_art.coords.get(RADIUS_ART).or_else(|| {
+            // Older exports name the ring differently, any of its variants will do.
+            radius_art
+                .coords
+                .iter()
+                .min_by_key(|(name, _)| name.as_str())
+                .map(|(_, sprite)| sprite)
+        });
+        for (&socket, jewel) in &self.build.jewels {
+            let (Some(band), Some(index)) = (jewel.band(), graph.index_of(socket)) else {
+                continue;
+            };
+            let center = graph.positions[index];
+            if !view.intersects(Rect::from_center_size(
+                center,
+                Vec2::splat(band.outer * 2.0),
+            )) {
+                continue;
+            }
+            let (tint, edge) = if self.build.is_allocated(socket) {
+                (Color32::WHITE, RADIUS_EDGE)
+            } else {
+                (DIMMED_TINT, DIMMED_TINT)
+            };
+            match ring {
+                Some(sprite) => draw_asset_sized(
+                    sprite,
+                    radius_art,
+                    self.jewel_radius_tex,
+                    Rect::from_center_size(center, Vec2::splat(band.outer * 2.0)),
+                    tint,
+                    &painter,
+                    &self.camera,
+                ),
+                None => {
+                    painter.circle_stroke(
+                        self.camera.world_to_screen(center),
+                        band.outer * self.camera.zoom,
+                        Stroke::new(2.0, edge),
+                    );
+                }
+            }
+            // Thread of Hope only reaches a ring, so mark where it starts.
+            if band.inner > 0.0 {
+                painter.circle_stroke(
+                    self.camera.world_to_screen(center),
+                    band.inner * self.camera.zoom,
+                    Stroke::new(2.0, edge),
+                );
+            }
         }
 
         let normal_active = &self.tree.sprites.normal_active.sprites;
+        let worse_sheet = |frame_type: FrameType| {
+            let sprites = &self.tree.sprites;
+            match frame_type {
+                FrameType::Keystone => &sprites.keystone_active.worse_sprites,
+                FrameType::Notable => &sprites.notable_active.worse_sprites,
+                _ => &sprites.normal_active.worse_sprites,
+            }
+        };
         let mastery_inactive = &self.tree.sprites.mastery_inactive.sprites;
+        let frames = &self.tree.sprites.frame.sprites;
+        let jewel_art = &self.tree.sprites.jewel.sprites;
 
-        for (node_id, node) in &self.tree.nodes {
+        for (index, node) in graph.nodes.iter().enumerate() {
+            let node_pos = graph.positions[index];
             if let Some(class_start_index) = node.class_start_index {
                 let tex_name = if class_start_index == self.selected_class.id() as usize {
                     CLASS_ART[class_start_index]
                 } else {
                     "PSStartNodeBackgroundInactive"
                 };
-                let node_pos = self.tree.node_position(node);
                 let sprite_info = self
                     .tree
                     .sprites
@@ -115,107 +353,317 @@ impl TemplateApp {
                     .coords
                     .get(tex_name)
                     .unwrap();
+                if !view.intersects(sprite_extent(sprite_info, node_pos, false)) {
+                    continue;
+                }
+                stats.nodes += 1;
                 draw_asset(
                     sprite_info,
                     &self.tree.sprites.start_node.sprites,
                     self.group_bg_tex,
-                    pos2(node_pos.0, node_pos.1),
+                    node_pos,
                     false,
                     &painter,
                     &self.camera,
                 );
                 continue;
             }
-            if node.is_proxy.unwrap_or(false) {
+            if node.is_proxy.unwrap_or(false) || node.is_ascendancy_start {
                 continue;
             }
-            if node.is_ascendancy_start {
+            if !node_view.contains(node_pos) {
                 continue;
             }
-            let node_is_hovered = node_id == hovered_node;
+            stats.nodes += 1;
+            let node_is_hovered = hovered_node == Some(index)
+                || node.skill.is_some_and(|skill| preview.contains(&skill));
+            let node_is_allocated = node
+                .skill
+                .is_some_and(|skill| self.build.is_allocated(skill));
+            let tint = if node.skill.is_some_and(|skill| refund.contains(&skill)) {
+                REFUND_TINT
+            } else if node.ascendancy_name.is_some()
+                && node.ascendancy_name.as_deref() != ascendancy
+            {
+                DIMMED_TINT
+            } else {
+                Color32::WHITE
+            };
             if node.is_mastery {
-                if let Some(sprite_info) = mastery_inactive.coords.get(&node.inactive_icon) {
-                    let nodepos = self.tree.node_position(node);
-                    draw_asset(
+                let skill = node.skill.unwrap_or_default();
+                let sprites = &self.tree.sprites;
+                let (sheet, texture, icon) = if node_is_allocated {
+                    (
+                        &sprites.mastery_active_selected.sprites,
+                        self.mastery_selected_tex,
+                        &node.active_icon,
+                    )
+                } else if self.build.can_take_mastery(graph, skill) {
+                    (
+                        &sprites.mastery_connected.sprites,
+                        self.mastery_connected_tex,
+                        &node.active_icon,
+                    )
+                } else {
+                    (mastery_inactive, self.mastery_tex, &node.inactive_icon)
+                };
+                if let Some(sprite_info) = sheet.coords.get(icon) {
+                    draw_asset_tinted(
                         sprite_info,
-                        mastery_inactive,
-                        self.mastery_tex,
-                        pos2(nodepos.0, nodepos.1),
-                        false,
+                        sheet,
+                        texture,
+                        node_pos,
+                        tint,
                         &painter,
                         &self.camera,
                     );
                 }
                 continue;
             }
-            if let Some(icon) = &node.icon {
-                let appropriate_sheet = match node.frame_type() {
-                    FrameType::Keystone => &self.tree.sprites.keystone_active.sprites,
-                    FrameType::Notable => &self.tree.sprites.notable_active.sprites,
-                    FrameType::Normal => normal_active,
-                    _ => normal_active,
-                };
-                if let Some(sprite_info) = appropriate_sheet.coords.get(icon) {
-                    let nodepos = self.tree.node_position(node);
-                    let tex = match node_id == hovered_node {
-                        true => self.skills_tex,
-                        false => self.inactive_skills_tex,
+            if node.is_jewel_socket {
+                let jewel = node.skill.and_then(|skill| self.build.jewels.get(&skill));
+                let art = jewel.and_then(|jewel| jewel_art.coords.get(jewel.base.socket_art()));
+                if let Some(sprite_info) = art {
+                    // Jewels in refunded sockets stay put but don't do anything.
+                    let tint = if node_is_allocated { tint } else { DIMMED_TINT };
+                    draw_asset_tinted(
+                        sprite_info,
+                        jewel_art,
+                        self.jewel_tex,
+                        node_pos,
+                        tint,
+                        &painter,
+                        &self.camera,
+                    );
+                }
+                let frame = frame_name(node.frame_type(), node_is_allocated, node_is_hovered)
+                    .and_then(|name| frames.coords.get(name))
+                    .filter(|_| !outline);
+                if let Some(frame) = frame {
+                    draw_asset_tinted(
+                        frame,
+                        frames,
+                        self.frame_tex,
+                        node_pos,
+                        tint,
+                        &painter,
+                        &self.camera,
+                    );
+                }
+                continue;
+            }
+            let appropriate_sheet = match node.frame_type() {
+                FrameType::Keystone => &self.tree.sprites.keystone_active.sprites,
+                FrameType::Notable => &self.tree.sprites.notable_active.sprites,
+                FrameType::Normal => normal_active,
+                _ => normal_active,
+            };
+            // Cluster jewel nodes have no icon in the export, so they're drawn as a bare frame.
+            let icon = node
+                .icon
+                .as_ref()
+                .and_then(|icon| appropriate_sheet.coords.get(icon));
+            if icon.is_none() && !graph.is_generated(index) {
+                continue;
+            }
+            let active = node_is_allocated || node_is_hovered;
+            let worse = node
+                .icon
+                .as_ref()
+                .and_then(|icon| worse_sheet(node.frame_type()).coords.get(icon))
+                .filter(|_| reduced);
+            match (icon, worse) {
+                (Some(sprite_info), Some(worse_info)) => {
+                    let tex = if active {
+                        self.skills_worse_tex
+                    } else {
+                        self.inactive_skills_worse_tex
                     };
-
-                    draw_asset(
+                    // Sized from the regular sprite so nodes don't shrink when the sheet changes.
+                    draw_asset_sized(
+                        worse_info,
+                        worse_sheet(node.frame_type()),
+                        tex,
+                        sprite_extent(sprite_info, node_pos, false),
+                        tint,
+                        &painter,
+                        &self.camera,
+                    );
+                }
+                (Some(sprite_info), None) => {
+                    let tex = if active {
+                        self.skills_tex
+                    } else {
+                        self.inactive_skills_tex
+                    };
+                    draw_asset_tinted(
                         sprite_info,
                         appropriate_sheet,
                         tex,
-                        pos2(nodepos.0, nodepos.1),
-                        false,
+                        node_pos,
+                        tint,
                         &painter,
                         &self.camera,
                     );
-                    let frame_name_opt = match node.frame_type() {
-                        FrameType::Keystone => {
-                            if node_is_hovered {
-                                Some("KeystoneFrameCanAllocate")
-                            } else {
-                                Some("KeystoneFrameUnallocated")
-                            }
-                        }
-                        FrameType::Notable => {
-                            if node_is_hovered {
-                                Some("NotableFrameAllocated")
-                            } else {
-                                Some("NotableFrameCanAllocate")
-                            }
-                        }
-                        FrameType::Normal => {
-                            if node_is_hovered {
-                                Some("PSSkillFrameHighlighted")
-                            } else {
-                                Some("PSSkillFrame")
-                            }
-                        }
-                        _ => None,
-                    };
-                    if let Some(frame_name) = frame_name_opt {
-                        let frame = self
-                            .tree
-                            .sprites
-                            .frame
-                            .sprites
-                            .coords
-                            .get(frame_name)
-                            .unwrap();
-                        draw_asset(
-                            frame,
-                            &self.tree.sprites.frame.sprites,
-                            self.frame_tex,
-                            pos2(nodepos.0, nodepos.1),
-                            false,
-                            &painter,
-                            &self.camera,
-                        );
-                    }
                 }
+                _ => {}
+            }
+            if outline {
+                continue;
+            }
+            let frame_name_opt = frame_name(node.frame_type(), node_is_allocated, node_is_hovered);
+            if let Some(frame_name) = frame_name_opt {
+                let frame = self
+                    .tree
+                    .sprites
+                    .frame
+                    .sprites
+                    .coords
+                    .get(frame_name)
+                    .unwrap();
+                draw_asset_tinted(
+                    frame,
+                    &self.tree.sprites.frame.sprites,
+                    self.frame_tex,
+                    node_pos,
+                    tint,
+                    &painter,
+                    &self.camera,
+                );
             }
         }
+
+        for (position, &index) in self.search.results.iter().enumerate() {
+            if !node_view.contains(graph.positions[index]) {
+                continue;
+            }
+            let (width, color) = if position == self.search.current {
+                (3.0, SEARCH_CURRENT)
+            } else {
+                (2.0, SEARCH_MATCH)
+            };
+            painter.circle_stroke(
+                self.camera.world_to_screen(graph.positions[index]),
+                (60.0 * self.camera.zoom).max(6.0),
+                Stroke::new(width, color),
+            );
+        }
+
+        let hovered = hovered_node.filter(|&index| {
+            let node = &graph.nodes[index];
+            node.name.is_some() && !node.is_proxy.unwrap_or(false)
+        });
+        if let Some(index) = hovered {
+            let node = &graph.nodes[index];
+            let cost = match &hover_path {
+                _ if over_budget => Some("Not enough ascendancy points left".to_string()),
+                Some(path) => Some(points_label("Costs", path.len())),
+                None if !refund.is_empty() => Some(points_label("Refunds", refund.len())),
+                None => None,
+            };
+            let effect = node
+                .skill
+                .and_then(|skill| self.build.mastery_effects.get(&skill).copied());
+            let jewel = node.skill.and_then(|skill| {
+                let jewel = self.build.jewels.get(&skill)?;
+                let in_radius = jewel.band().map(|_| {
+                    jewel
+                        .affected_nodes(graph, skill)
+                        .into_iter()
+                        .filter(|&index| self.build.is_allocated(graph.skill(index)))
+                        .count()
+                });
+                Some((jewel, in_radius))
+            });
+            let replacement = timeless::replacement_at(&self.timeless, graph, &self.build, index);
+            response.on_hover_ui_at_pointer(|ui| {
+                node_tooltip(ui, node, replacement, effect, jewel, cost)
+            });
+        }
+        self.render_stats = stats;
+        if let Some(socket) = edit_jewel {
+            self.open_jewel_editor(socket);
+        }
+    }
+}
+
+fn points_label(verb: &str, points: usize) -> String {
+    format!(
+        "{verb} {points} point{}",
+        if points == 1 { "" } else { "s" }
+    )
+}
+
+fn node_tooltip(
+    ui: &mut Ui,
+    node: &Node,
+    replacement: Option<&Replacement>,
+    effect: Option<usize>,
+    jewel: Option<(&Jewel, Option<usize>)>,
+    cost: Option<String>,
+) {
+    ui.set_max_width(320.0);
+    let original = node.name.as_deref().unwrap_or_default();
+    let (name, stats) = match replacement {
+        Some(replacement) => (replacement.name.as_str(), &replacement.stats),
+        None => (original, &node.stats),
+    };
+    ui.label(RichText::new(name).strong().color(TOOLTIP_NAME));
+    ui.label(RichText::new(node.kind()).small().weak());
+    if replacement.is_some() {
+        ui.label(
+            RichText::new(format!("Replaced {original} by timeless jewel"))
+                .small()
+                .weak(),
+        );
+    }
+    if !stats.is_empty() {
+        ui.separator();
+        for stat in stats {
+            ui.label(RichText::new(stat).color(TOOLTIP_STAT));
+        }
+    }
+    if !node.mastery_effects.is_empty() {
+        ui.separator();
+        for option in &node.mastery_effects {
+            let color = if effect == Some(option.effect) {
+                TOOLTIP_STAT
+            } else {
+                Color32::GRAY
+            };
+            for stat in &option.stats {
+                ui.label(RichText::new(stat).color(color));
+            }
+        }
+    }
+    if let Some((jewel, in_radius)) = jewel {
+        ui.separator();
+        ui.label(RichText::new(&jewel.name).strong());
+        ui.label(RichText::new(jewel.base.base_name()).small().weak());
+        for line in &jewel.mods {
+            ui.label(RichText::new(line).color(TOOLTIP_STAT));
+        }
+        if let Some(count) = in_radius {
+            ui.label(RichText::new(format!("{count} allocated passive(s) in radius")).weak());
+        }
+    } else if node.is_jewel_socket {
+        ui.label(
+            RichText::new("Click when allocated to socket a jewel")
+                .small()
+                .weak(),
+        );
+    }
+    for reminder in &node.reminder_text {
+        ui.label(RichText::new(reminder).italics().weak());
+    }
+    if !node.flavour_text.is_empty() {
+        ui.separator();
+        for flavour in &node.flavour_text {
+            ui.label(RichText::new(flavour).italics().color(TOOLTIP_FLAVOUR));
+        }
+    }
+    if let Some(cost) = cost {
+        ui.separator();
+        ui.label(cost);
     }
 }
@@ -0,0 +1,124 @@
+use egui::{Color32, Ui};
+
+use crate::{search, search::SearchMode, TemplateApp};
+
+use super::model::NodeSearch;
+
+impl NodeSearch {
+    /// Moves on to the next match, wrapping around after the last.
+    pub fn next(&mut self) {
+        if !self.results.is_empty() {
+            self.current = (self.current + 1) % self.results.len();
+        }
+    }
+
+    /// Moves back to the previous match, wrapping around before the first.
+    pub fn previous(&mut self) {
+        let count = self.results.len();
+        if count > 0 {
+            self.current = (self.current + count - 1) % count;
+        }
+    }
+}
+
+impl TemplateApp {
+    pub fn search_bar(&mut self, ui: &mut Ui) {
+        let query = ui.add(
+            egui::TextEdit::singleline(&mut self.search.query)
+                .hint_text("Search nodes")
+                .desired_width(160.0),
+        );
+        let mut regex = self.search.mode == SearchMode::Regex;
+        let mode = ui
+            .checkbox(&mut regex, ".*")
+            .on_hover_text("Treat the search as a regular expression");
+        if query.changed() || mode.changed() {
+            self.search.mode = if regex {
+                SearchMode::Regex
+            } else {
+                SearchMode::Substring
+            };
+            self.refresh_search();
+            self.focus_search_match();
+        }
+
+        if let Some(error) = &self.search.error {
+            ui.colored_label(Color32::LIGHT_RED, "Invalid regex")
+                .on_hover_text(error);
+            return;
+        }
+        if self.search.query.is_empty() {
+            return;
+        }
+        let count = self.search.results.len();
+        if count == 0 {
+            ui.label("No matches");
+            return;
+        }
+        ui.label(format!("{} of {count}", self.search.current + 1));
+        if ui
+            .small_button("◀")
+            .on_hover_text("Previous match")
+            .clicked()
+        {
+            self.search.previous();
+            self.focus_search_match();
+        }
+        if ui.small_button("▶").on_hover_text("Next match").clicked() {
+            self.search.next();
+            self.focus_search_match();
+        }
+        if query.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            self.search.next();
+            self.focus_search_match();
+            query.request_focus();
+        }
+    }
+
+    fn focus_search_match(&mut self) {
+        if let Some(&index) = self.search.results.get(self.search.current) {
+            self.focus_request = Some(index);
+        }
+    }
+
+    /// Re-runs the search, e.g. after the query changed or another tree was loaded.
+    pub fn refresh_search(&mut self) {
+        self.search.current = 0;
+        match search::search(&self.tree.graph, &self.search.query, self.search.mode) {
+            Ok(results) => {
+                self.search.results = results;
+                self.search.error = None;
+            }
+            Err(e) => {
+                self.search.results.clear();
+                self.search.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_through_matches() {
+        let mut search = NodeSearch {
+            results: vec![4, 9, 2],
+            ..Default::default()
+        };
+        search.previous();
+        assert_eq!(search.current, 2);
+        search.next();
+        assert_eq!(search.current, 0);
+        search.next();
+        search.next();
+        search.next();
+        assert_eq!(search.current, 0);
+
+        let mut empty = NodeSearch::default();
+        empty.next();
+        empty.previous();
+        assert_eq!(empty.current, 0);
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+
+use crate::{
+    build::Build,
+    cluster::{self, GENERATED_ID_BASE},
+    pathing, pob,
+    tree_url::{self, TreeUrl},
+    TemplateApp,
+};
+
+use super::model::CharacterClass;
+
+impl TemplateApp {
+    /// Replaces the current class and build, as long as the build is valid on the loaded tree.
+    fn apply_build(&mut self, class: CharacterClass, mut build: Build) -> Result<(), String> {
+        // Cluster jewel nodes only exist once the new build's jewels have generated them.
+        cluster::generate(&mut self.tree.graph, &build.jewels);
+        let checked = self.check_build(&class, &build);
+        if checked.is_ok() {
+            self.selected_class = class;
+            build.tree_version.clone_from(&self.tree.tree);
+            self.build = build;
+        }
+        self.regenerate_clusters();
+        checked
+    }
+
+    fn check_build(&self, class: &CharacterClass, build: &Build) -> Result<(), String> {
+        let graph = &self.tree.graph;
+        if graph.class_start(class.id() as usize).is_none() {
+            return Err("the loaded tree has no start node for this class".to_string());
+        }
+        let class_id = class.id() as usize;
+        if build.ascendancy as usize > self.tree.ascendancies(class_id).len() {
+            return Err(format!("unknown ascendancy id {}", build.ascendancy));
+        }
+        let ascendancy = self.tree.ascendancy(class_id, build.ascendancy);
+        let starts = graph.start_nodes(class_id, ascendancy.map(|a| a.id.as_str()));
+        build.validate(graph, &starts).map_err(|e| e.to_string())
+    }
+
+    /// Imports the class, ascendancy, passives and masteries of a tree URL. Cluster jewel
+    /// nodes can't be, since the URL doesn't say which jewels made them, so when it lists any
+    /// the rest is still imported and what was left out comes back as a notice.
+    pub fn import_tree_url(
+        &mut self,
+        _ctx: &egui::Context,
+        input: &str,
+    ) -> Result<Option<String>, String> {
+        let url = tree_url::decode(input).map_err(|e| e.to_string())?;
+        let class = CharacterClass::from_id(url.class as i32)
+            .ok_or_else(|| format!("unknown class id {}", url.class))?;
+        url.check_ascendancy(&self.tree)
+            .map_err(|e| e.to_string())?;
+        let mut allocated: HashSet<usize> = url.nodes.iter().map(|&node| node as usize).collect();
+        let mut unreachable = 0;
+        if !url.cluster_nodes.is_empty() {
+            // Sockets nested in the clusters, and whatever hangs off them, go with them.
+            let class_id = class.id() as usize;
+            let ascendancy = self.tree.ascendancy(class_id, url.ascendancy);
+            let starts = self
+                .tree
+                .graph
+                .start_nodes(class_id, ascendancy.map(|a| a.id.as_str()));
+            let connected = pathing::connected_to_start(&self.tree.graph, &allocated, &starts);
+            unreachable = allocated.len() - connected.len();
+            allocated = connected;
+        }
+        let build = Build {
+            tree_version: Default::default(),
+            allocated,
+            ascendancy: url.ascendancy,
+            mastery_effects: url
+                .mastery_effects
+                .iter()
+                .map(|&(node, effect)| (node as usize, effect as usize))
+                .collect(),
+            progress: self.build.progress.clone(),
+            jewels: Default::default(),
+        };
+
+        self.apply_build(class, build)?;
+        Ok(match (url.cluster_nodes.len(), unreachable) {
+            (0, _) => None,
+            (dropped, 0) => Some(format!(
+                "Imported without {dropped} cluster jewel node(s), tree URLs don't carry the \
+                 jewels that add them"
+            )),
+            (dropped, unreachable) => Some(format!(
+                "Imported without {dropped} cluster jewel node(s) and the {unreachable} \
+                 passive(s) past them, tree URLs don't carry the jewels that add them"
+            )),
+        })
+    }
+
+    /// Cluster jewel nodes, and passives only reachable through them, are left out: a URL can't
+    /// carry the jewels needed to read them back.
+    pub fn export_tree_url(&self) -> Result<String, String> {
+        let to_u16 =
+            |id: usize| u16::try_from(id).map_err(|_| format!("node id {id} is too large"));
+        let allocated: HashSet<usize> = self
+            .build
+            .allocated
+            .iter()
+            .copied()
+            .filter(|&skill| skill < GENERATED_ID_BASE)
+            .collect();
+        let allocated =
+            pathing::connected_to_start(&self.tree.graph, &allocated, &self.start_nodes());
+        let mut nodes = allocated
+            .iter()
+            .copied()
+            .map(to_u16)
+            .collect::<Result<Vec<u16>, String>>()?;
+        nodes.sort_unstable();
+        let mut mastery_effects = self
+            .build
+            .mastery_effects
+            .iter()
+            .filter(|(node, _)| allocated.contains(node))
+            .map(|(&node, &effect)| Ok((to_u16(node)?, to_u16(effect)?)))
+            .collect::<Result<Vec<(u16, u16)>, String>>()?;
+        mastery_effects.sort_unstable();
+
+        tree_url::encode(&TreeUrl {
+            class: self.selected_class.id() as u8,
+            ascendancy: self.build.ascendancy,
+            nodes,
+            cluster_nodes: vec![],
+            mastery_effects,
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    pub fn import_pob_code(
+        &mut self,
+        ctx: &egui::Context,
+        input: &str,
+    ) -> Result<Option<String>, String> {
+        let pob = pob::decode(input).map_err(|e| e.to_string())?;
+        let class = CharacterClass::from_id(pob.class_id as i32)
+            .ok_or_else(|| format!("unknown class id {}", pob.class_id))?;
+
+        // Check the build against the tree it was made on when that's loaded, and put
+        // everything back if it doesn't fit.
+        let previous_version = self.tree.tree.clone();
+        let previous_build = std::mem::take(&mut self.build);
+        let mut progress = previous_build.progress.clone();
+        if let Some(level) = pob
+            .build_attributes
+            .iter()
+            .find(|(name, _)| name == "level")
+            .and_then(|(_, level)| level.parse().ok())
+        {
+            progress.level = level;
+        }
+        let switched = self.switch_tree(ctx, &pob.tree_version);
+
+        let graph = &self.tree.graph;
+        // PoB lists class and ascendancy starts as allocated, the planner treats them as implied.
+        let allocated = pob
+            .nodes
+            .iter()
+            .copied()
+            .filter(|&skill| {
+                graph.node(skill).map_or(true, |node| {
+                    node.class_start_index.is_none() && !node.is_ascendancy_start
+                })
+            })
+            .collect();
+        let mut jewels = std::collections::HashMap::new();
+        for &(socket, item_id) in &pob.sockets {
+            let jewel = pob
+                .items
+                .iter()
+                .find(|item| item.id == item_id)
+                .and_then(|item| pob::parse_jewel_item(&item.text));
+            match jewel {
+                Some(jewel) => {
+                    jewels.insert(socket, jewel);
+                }
+                None => log::warn!("Ignoring unsupported item {item_id} in socket {socket}"),
+            }
+        }
+        let build = Build {
+            tree_version: Default::default(),
+            allocated,
+            ascendancy: pob.ascendancy_id,
+            mastery_effects: pob.mastery_effects.iter().copied().collect(),
+            progress,
+            jewels,
+        };
+
+        if let Err(e) = self.apply_build(class, build) {
+            if switched {
+                self.switch_tree(ctx, &previous_version);
+            }
+            self.build = previous_build;
+            self.regenerate_clusters();
+            return Err(e);
+        }
+        self.notes_buffer.clone_from(&pob.notes);
+        self.pob_document = Some(pob);
+        Ok(None)
+    }
+
+    pub fn export_pob_code(&self) -> String {
+        let class = &self.selected_class;
+        let ascendancy = self.selected_ascendancy().map(|a| a.name.as_str());
+
+        let mut pob = self.pob_document.clone().unwrap_or_default();
+        pob.class_id = class.id() as u8;
+        pob.ascendancy_id = self.build.ascendancy;
+        pob.tree_version.clone_from(&self.tree.tree);
+        pob.nodes = self
+            .build
+            .allocated
+            .iter()
+            .copied()
+            .chain(self.start_nodes())
+            .collect();
+        pob.nodes.sort_unstable();
+        pob.mastery_effects = self
+            .build
+            .mastery_effects
+            .iter()
+            .map(|(&n, &e)| (n, e))
+            .collect();
+        pob.mastery_effects.sort_unstable();
+        pob.notes.clone_from(&self.notes_buffer);
+        export_jewels(&self.build, &mut pob);
+
+        pob.set_build_attribute("className", format!("{class:?}"));
+        pob.set_build_attribute("ascendClassName", ascendancy.unwrap_or("None").to_string());
+        pob.set_build_attribute("level", self.build.progress.level.to_string());
+
+        pob::encode(&pob)
+    }
+}
+
+/// Puts the build's jewels into `pob`'s sockets. Items are only rewritten when the jewel in
+/// them was changed, and sockets holding items the planner can't read are left alone.
+fn export_jewels(build: &Build, pob: &mut pob::PobBuild) {
+    let previous = std::mem::take(&mut pob.sockets);
+    for &(socket, id) in &previous {
+        let managed = pob
+            .items
+            .iter()
+            .find(|item| item.id == id)
+            .is_some_and(|item| pob::parse_jewel_item(&item.text).is_some());
+        if !managed && !build.jewels.contains_key(&socket) {
+            pob.sockets.push((socket, id));
+        }
+    }
+
+    let mut sockets: Vec<usize> = build.jewels.keys().copied().collect();
+    sockets.sort_unstable();
+    for socket in sockets {
+        let jewel = &build.jewels[&socket];
+        let socketed = previous
+            .iter()
+            .find(|&&(node, _)| node == socket)
+            .and_then(|&(_, id)| pob.items.iter_mut().find(|item| item.id == id));
+        let id = match socketed {
+            Some(item) => {
+                if pob::parse_jewel_item(&item.text).as_ref() != Some(jewel) {
+                    item.text = pob::jewel_item(jewel);
+                    item.children.clear();
+                }
+                item.id
+            }
+            None => {
+                let id = pob
+                    .items
+                    .iter()
+                    .map(|item| item.id)
+                    .max()
+                    .unwrap_or_default()
+                    + 1;
+                pob.items.push(pob::PobItem {
+                    id,
+                    text: pob::jewel_item(jewel),
+                    children: vec![],
+                });
+                id
+            }
+        };
+        pob.sockets.push((socket, id));
+    }
+    pob.sockets.sort_unstable();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jewel::{Jewel, JewelBase};
+
+    const MAGIC: &str = "Rarity: MAGIC\nVivid Cobalt Jewel of the Wolf\nItem Level: 83\n\
+                         Implicits: 0\n{range:0.4}+10 to Intelligence";
+    const RARE: &str = "Rarity: RARE\nGlyph Spark\nMurderous Eye Jewel\nItem Level: 84\n\
+                        Implicits: 0\n{variant:1}+8% to Fire Resistance";
+    const UNKNOWN: &str = "Rarity: RARE\nDoom Loop\nGhastly Eye Jewel of Doom";
+
+    fn item(id: usize, text: &str) -> pob::PobItem {
+        pob::PobItem {
+            id,
+            text: text.to_string(),
+            children: vec!["<ModRange id=\"1\" range=\"0.4\"/>".to_string()],
+        }
+    }
+
+    /// A PoB document with three socketed items, read into a build the way the import does.
+    fn imported() -> (pob::PobBuild, Build) {
+        let pob = pob::PobBuild {
+            items: vec![item(1, MAGIC), item(2, RARE), item(3, UNKNOWN)],
+            sockets: vec![(100, 1), (200, 2), (300, 3)],
+            ..Default::default()
+        };
+        let mut build = Build::default();
+        for &(socket, id) in &pob.sockets {
+            if let Some(jewel) = pob::parse_jewel_item(&pob.items[id - 1].text) {
+                build.jewels.insert(socket, jewel);
+            }
+        }
+        (pob, build)
+    }
+
+    #[test]
+    fn leaves_unchanged_jewels_alone() {
+        let (mut pob, build) = imported();
+        assert_eq!(build.jewels.len(), 2);
+        let before = pob.clone();
+        export_jewels(&build, &mut pob);
+        assert_eq!(pob, before);
+    }
+
+    #[test]
+    fn rewrites_edited_jewels_only() {
+        let (mut pob, mut build) = imported();
+        let edited = build.jewels.get_mut(&200).unwrap();
+        edited.mods.push("5% increased maximum Life".to_string());
+        build.jewels.remove(&100);
+        build.jewels.insert(400, Jewel::new(JewelBase::Crimson));
+        export_jewels(&build, &mut pob);
+
+        assert_eq!(pob.sockets, [(200, 2), (300, 3), (400, 4)]);
+        let by_id = |id: usize| pob.items.iter().find(|item| item.id == id).unwrap();
+        assert_eq!(by_id(1).text, MAGIC);
+        assert_eq!(by_id(2).text, pob::jewel_item(&build.jewels[&200]));
+        assert!(by_id(2).children.is_empty());
+        assert_eq!(by_id(3).text, UNKNOWN);
+        assert_eq!(by_id(3).children.len(), 1);
+        assert_eq!(
+            pob::parse_jewel_item(&by_id(4).text).as_ref(),
+            Some(&build.jewels[&400])
+        );
+    }
+}
@@ -1,6 +1,10 @@
-use egui::Ui;
+use egui::{Color32, RichText, Ui};
 
-use crate::TemplateApp;
+use crate::{
+    build::{LABYRINTHS, MAX_LEVEL, QUEST_REWARDS},
+    graph::NodeIndex,
+    pathing, stats, TemplateApp,
+};
 
 use super::model::View;
 
@@ -20,15 +24,170 @@ impl TemplateApp {
             if ui.button("Calcs").clicked() {};
             if ui.button("Party").clicked() {};
         });
-        ui.label("Main Skill:");
-        let mut selected = "";
-        egui::ComboBox::from_id_source("main_skill_combobox")
-            .selected_text(selected)
-            .show_ui(ui, |ui| {
-                ui.selectable_value(&mut selected, "First", "First");
-                ui.selectable_value(&mut selected, "Second", "Second");
-            });
         ui.separator();
-        ui.label(format!("{}", self.camera.zoom));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.point_budget(ui);
+            ui.separator();
+            self.keystone_browser(ui);
+            ui.separator();
+            self.stat_summary(ui);
+        });
+    }
+
+    fn point_budget(&mut self, ui: &mut Ui) {
+        let graph = &self.tree.graph;
+        let passive_used = self.build.passive_points_used(graph);
+        let ascendancy_used = self.build.ascendancy_points_used(graph);
+        let progress = &mut self.build.progress;
+        budget_label(
+            ui,
+            "Passive points",
+            passive_used,
+            progress.passive_points(),
+        );
+        budget_label(
+            ui,
+            "Ascendancy points",
+            ascendancy_used,
+            progress.ascendancy_points(),
+        );
+
+        ui.horizontal(|ui| {
+            ui.label("Level");
+            ui.add(egui::DragValue::new(&mut progress.level).range(1..=MAX_LEVEL));
+        });
+        egui::CollapsingHeader::new(format!("Quest rewards ({})", progress.quest_points())).show(
+            ui,
+            |ui| {
+                for quest in &QUEST_REWARDS {
+                    let mut done = !progress.missed_quests.contains(quest.name);
+                    let text = format!("Act {}: {} (+{})", quest.act, quest.name, quest.points);
+                    if ui.checkbox(&mut done, text).changed() {
+                        if done {
+                            progress.missed_quests.remove(quest.name);
+                        } else {
+                            progress.missed_quests.insert(quest.name.to_string());
+                        }
+                    }
+                }
+            },
+        );
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Labyrinths");
+            for (completed, name) in LABYRINTHS.iter().enumerate() {
+                let mut done = (progress.labyrinths as usize) > completed;
+                if ui.checkbox(&mut done, *name).changed() {
+                    // Labyrinths unlock in order, so ticking one ticks everything before it.
+                    progress.labyrinths = if done { completed + 1 } else { completed } as u8;
+                }
+            }
+        });
+    }
+
+    fn keystone_browser(&mut self, ui: &mut Ui) {
+        let graph = &self.tree.graph;
+        let starts = self.start_nodes();
+        let mut keystones: Vec<NodeIndex> = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_keystone)
+            .map(|(index, _)| index)
+            .collect();
+        keystones.sort_by(|&a, &b| graph.nodes[a].name.cmp(&graph.nodes[b].name));
+
+        let mut go_to = None;
+        let mut allocate = None;
+        egui::CollapsingHeader::new(RichText::new("Keystones").strong()).show(ui, |ui| {
+            for index in keystones {
+                let node = &graph.nodes[index];
+                let skill = graph.skill(index);
+                let allocated = self.build.is_allocated(skill);
+                let path = pathing::shortest_path(graph, &self.build, &starts, skill);
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(node.name.as_deref().unwrap_or_default()).strong());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("Go to").clicked() {
+                            go_to = Some(index);
+                        }
+                        if let Some(path) = &path {
+                            if ui.small_button("Allocate").clicked() {
+                                allocate = Some(path.clone());
+                            }
+                        }
+                    });
+                });
+                let cost = match &path {
+                    _ if allocated => "Allocated".to_string(),
+                    Some(path) => format!("{} point(s) away", path.len()),
+                    None => "Unreachable".to_string(),
+                };
+                ui.weak(cost);
+                for stat in &node.stats {
+                    ui.label(RichText::new(stat).small());
+                }
+                ui.add_space(4.0);
+            }
+        });
+
+        if let Some(index) = go_to {
+            self.selected_view = View::PassiveTree;
+            self.focus_request = Some(index);
+        }
+        if let Some(path) = allocate {
+            self.build.allocate_path(&path);
+        }
+    }
+
+    fn stat_summary(&mut self, ui: &mut Ui) {
+        let summary = self
+            .stat_cache
+            .summary(&self.tree, &self.build, &self.timeless);
+        if let Some(class) = self.tree.classes.get(self.selected_class.id() as usize) {
+            let total = stats::attributes(class, summary);
+            ui.horizontal_wrapped(|ui| {
+                for (name, value, base) in [
+                    ("Str", total.strength, class.base_str),
+                    ("Dex", total.dexterity, class.base_dex),
+                    ("Int", total.intelligence, class.base_int),
+                ] {
+                    ui.label(RichText::new(format!("{name}: {value}")).strong())
+                        .on_hover_text(format!("{base} from {}", class.name));
+                }
+            });
+        }
+        if summary.categories.is_empty() {
+            ui.weak("Allocate passives to see their totals here.");
+        }
+        for (category, totals) in &summary.categories {
+            egui::CollapsingHeader::new(RichText::new(category.to_string()).strong())
+                .default_open(true)
+                .show(ui, |ui| {
+                    for total in totals {
+                        ui.label(total.to_string());
+                    }
+                });
+        }
+        if !summary.unparsed.is_empty() {
+            egui::CollapsingHeader::new(format!("Not counted ({})", summary.unparsed.len())).show(
+                ui,
+                |ui| {
+                    for line in &summary.unparsed {
+                        ui.colored_label(Color32::GRAY, line);
+                    }
+                },
+            );
+        }
+    }
+}
+
+fn budget_label(ui: &mut Ui, name: &str, used: usize, available: usize) {
+    let text = format!("{name}: {used} / {available}");
+    if used > available {
+        ui.colored_label(Color32::LIGHT_RED, text)
+            .on_hover_text(format!("{} over budget", used - available));
+    } else {
+        ui.label(text);
     }
 }
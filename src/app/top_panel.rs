@@ -1,11 +1,39 @@
-use egui::Ui;
+use egui::{Color32, Ui};
 
-use crate::TemplateApp;
+use crate::{pathing, tree::Ascendancy, TemplateApp};
 
 use super::model::CharacterClass;
 
 impl TemplateApp {
     pub fn top_panel(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            self.version_selector(ui);
+            self.class_selector(ui);
+            self.ascendancy_selector(ui);
+            ui.separator();
+            self.import_controls(ui);
+            ui.separator();
+            self.search_bar(ui);
+        });
+    }
+
+    fn version_selector(&mut self, ui: &mut Ui) {
+        let mut selected = self.tree.tree.clone();
+        egui::ComboBox::from_label("Tree")
+            .selected_text(&selected)
+            .show_ui(ui, |ui| {
+                for version in self.tree_versions() {
+                    ui.selectable_value(&mut selected, version.clone(), version);
+                }
+            });
+
+        if selected != self.tree.tree {
+            self.switch_tree(ui.ctx(), &selected);
+        }
+    }
+
+    fn class_selector(&mut self, ui: &mut Ui) {
+        let previous_class = self.selected_class.id();
         egui::ComboBox::from_label("Class")
             .selected_text(format!("{:?}", self.selected_class))
             .show_ui(ui, |ui| {
@@ -21,5 +49,115 @@ impl TemplateApp {
                 ui.selectable_value(&mut self.selected_class, CharacterClass::Templar, "Templar");
                 ui.selectable_value(&mut self.selected_class, CharacterClass::Shadow, "Shadow");
             });
+
+        if self.selected_class.id() != previous_class {
+            self.build.ascendancy = 0;
+            self.refund_disconnected();
+        }
+    }
+
+    fn ascendancy_selector(&mut self, ui: &mut Ui) {
+        let previous = self.build.ascendancy;
+        egui::ComboBox::from_label("Ascendancy")
+            .selected_text(
+                self.selected_ascendancy()
+                    .map_or("None", |ascendancy| ascendancy.name.as_str()),
+            )
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.build.ascendancy, 0, "None");
+                let ascendancies = self.tree.ascendancies(self.selected_class.id() as usize);
+                for (index, ascendancy) in ascendancies.iter().enumerate() {
+                    ui.selectable_value(
+                        &mut self.build.ascendancy,
+                        index as u8 + 1,
+                        &ascendancy.name,
+                    );
+                }
+            });
+
+        if self.build.ascendancy != previous {
+            self.refund_disconnected();
+        }
+    }
+
+    fn import_controls(&mut self, ui: &mut Ui) {
+        ui.add(
+            egui::TextEdit::singleline(&mut self.import_buffer)
+                .hint_text("Tree URL or PoB code")
+                .desired_width(240.0),
+        );
+        if ui.button("Import URL").clicked() {
+            self.run_import(ui.ctx(), Self::import_tree_url);
+        }
+        if ui.button("Import PoB").clicked() {
+            self.run_import(ui.ctx(), Self::import_pob_code);
+        }
+        if ui.button("Copy URL").clicked() {
+            match self.export_tree_url() {
+                Ok(url) => {
+                    self.import_error = None;
+                    ui.ctx().copy_text(url);
+                }
+                Err(e) => self.import_error = Some(e),
+            }
+        }
+        if ui.button("Copy PoB").clicked() {
+            self.import_error = None;
+            ui.ctx().copy_text(self.export_pob_code());
+        }
+        if let Some(error) = &self.import_error {
+            ui.colored_label(Color32::LIGHT_RED, error);
+        } else if let Some(notice) = &self.import_notice {
+            ui.colored_label(Color32::LIGHT_YELLOW, notice);
+        }
+    }
+
+    /// Clears the input on success and keeps it around for fixing up on failure.
+    fn run_import(
+        &mut self,
+        ctx: &egui::Context,
+        import: fn(&mut Self, &egui::Context, &str) -> Result<Option<String>, String>,
+    ) {
+        let input = std::mem::take(&mut self.import_buffer);
+        self.import_notice = None;
+        match import(self, ctx, &input) {
+            Ok(notice) => {
+                self.import_error = None;
+                self.import_notice = notice;
+            }
+            Err(e) => {
+                self.import_error = Some(e);
+                self.import_buffer = input;
+            }
+        }
+    }
+
+    /// The chosen ascendancy, as the loaded tree lists it for the selected class.
+    pub fn selected_ascendancy(&self) -> Option<&Ascendancy> {
+        self.tree
+            .ascendancy(self.selected_class.id() as usize, self.build.ascendancy)
+    }
+
+    /// Skill ids of the selected class start and ascendancy start.
+    pub fn start_nodes(&self) -> Vec<usize> {
+        self.tree.graph.start_nodes(
+            self.selected_class.id() as usize,
+            self.selected_ascendancy()
+                .map(|ascendancy| ascendancy.id.as_str()),
+        )
+    }
+
+    /// Drops whatever the current starts can no longer reach, e.g. after switching class.
+    pub fn refund_disconnected(&mut self) {
+        let starts = self.start_nodes();
+        let connected =
+            pathing::connected_to_start(&self.tree.graph, &self.build.allocated, &starts);
+        let disconnected = self
+            .build
+            .allocated
+            .difference(&connected)
+            .copied()
+            .collect();
+        self.build.refund(&disconnected);
     }
 }
@@ -2,9 +2,74 @@ use egui::{pos2, Color32, Painter, Pos2, Rect, TextureId, TextureOptions};
 
 use crate::{
     camera::Camera,
-    tree::{Sprite, SpriteCoords},
+    tree::{Sprite, SpriteCoords, TreeExport},
 };
 
+/// Sprite sheets shipped with the planner, under the file names the export refers to them by.
+const BUNDLED_SHEETS: [(&str, &[u8]); 13] = [
+    (
+        "group-background-3.png",
+        include_bytes!("../../resources/ggg_assets/group-background-3.png"),
+    ),
+    (
+        "background-3.png",
+        include_bytes!("../../resources/ggg_assets/background-3.png"),
+    ),
+    (
+        "skills-3.jpg",
+        include_bytes!("../../resources/ggg_assets/skills-3.jpg"),
+    ),
+    (
+        "frame-3.png",
+        include_bytes!("../../resources/ggg_assets/frame-3.png"),
+    ),
+    (
+        "skills-disabled-3.jpg",
+        include_bytes!("../../resources/ggg_assets/skills-disabled-3.jpg"),
+    ),
+    (
+        "skills-2.jpg",
+        include_bytes!("../../resources/ggg_assets/skills-2.jpg"),
+    ),
+    (
+        "skills-disabled-2.jpg",
+        include_bytes!("../../resources/ggg_assets/skills-disabled-2.jpg"),
+    ),
+    (
+        "mastery-disabled-3.png",
+        include_bytes!("../../resources/ggg_assets/mastery-disabled-3.png"),
+    ),
+    (
+        "mastery-connected-3.png",
+        include_bytes!("../../resources/ggg_assets/mastery-connected-3.png"),
+    ),
+    (
+        "mastery-active-selected-3.png",
+        include_bytes!("../../resources/ggg_assets/mastery-active-selected-3.png"),
+    ),
+    (
+        "jewel-3.png",
+        include_bytes!("../../resources/ggg_assets/jewel-3.png"),
+    ),
+    (
+        "jewel-radius.png",
+        include_bytes!("../../resources/ggg_assets/jewel-radius.png"),
+    ),
+    (
+        "ascendancy-background-2.jpg",
+        include_bytes!("../../resources/ggg_assets/ascendancy-background-2.jpg"),
+    ),
+];
+
+/// The area `draw_asset` covers in tree coordinates, for culling sprites off screen.
+pub fn sprite_extent(sprite: &SpriteCoords, pos: Pos2, is_half: bool) -> Rect {
+    let width = sprite.w as f32 * 1.33;
+    let height = sprite.h as f32 * 1.33;
+    // Half images are mirrored below their center, so they cover twice the height.
+    let height = if is_half { height * 2.0 } else { height };
+    Rect::from_center_size(pos, egui::vec2(width * 2.0, height * 2.0))
+}
+
 pub fn draw_asset(
     sprite: &SpriteCoords,
     sheet: &Sprite,
@@ -47,28 +112,112 @@ pub fn draw_asset(
         painter.image(texture, top_rect, top_uv, Color32::WHITE);
         painter.image(texture, bottom_rect, bottom_uv, Color32::WHITE);
     } else {
-        painter.image(
-            texture,
-            Rect::from_min_max(
-                camera.world_to_screen(pos2(pos.x - width, pos.y - height)),
-                camera.world_to_screen(pos2(pos.x + width, pos.y + height)),
+        draw_asset_tinted(sprite, sheet, texture, pos, Color32::WHITE, painter, camera);
+    }
+}
+
+pub fn draw_asset_tinted(
+    sprite: &SpriteCoords,
+    sheet: &Sprite,
+    texture: TextureId,
+    pos: Pos2,
+    tint: Color32,
+    painter: &Painter,
+    camera: &Camera,
+) {
+    let world = sprite_extent(sprite, pos, false);
+    painter.image(
+        texture,
+        Rect::from_min_max(
+            camera.world_to_screen(world.min),
+            camera.world_to_screen(world.max),
+        ),
+        Rect::from_min_max(
+            pos2(
+                sprite.x as f32 / sheet.w as f32,
+                sprite.y as f32 / sheet.h as f32,
             ),
-            Rect::from_min_max(
-                pos2(
-                    sprite.x as f32 / sheet.w as f32,
-                    sprite.y as f32 / sheet.h as f32,
-                ),
-                pos2(
-                    (sprite.x + sprite.w) as f32 / sheet.w as f32,
-                    (sprite.y + sprite.h) as f32 / sheet.h as f32,
-                ),
+            pos2(
+                (sprite.x + sprite.w) as f32 / sheet.w as f32,
+                (sprite.y + sprite.h) as f32 / sheet.h as f32,
             ),
-            Color32::WHITE,
-        );
+        ),
+        tint,
+    );
+}
+
+/// Draws `sprite` stretched over `world`, a rectangle in tree coordinates.
+pub fn draw_asset_sized(
+    sprite: &SpriteCoords,
+    sheet: &Sprite,
+    texture: TextureId,
+    world: Rect,
+    tint: Color32,
+    painter: &Painter,
+    camera: &Camera,
+) {
+    painter.image(
+        texture,
+        Rect::from_min_max(
+            camera.world_to_screen(world.min),
+            camera.world_to_screen(world.max),
+        ),
+        Rect::from_min_max(
+            pos2(
+                sprite.x as f32 / sheet.w as f32,
+                sprite.y as f32 / sheet.h as f32,
+            ),
+            pos2(
+                (sprite.x + sprite.w) as f32 / sheet.w as f32,
+                (sprite.y + sprite.h) as f32 / sheet.h as f32,
+            ),
+        ),
+        tint,
+    );
+}
+
+/// Strips the CDN path and cache-busting query from a sprite sheet's `filename`.
+pub fn sheet_file_name(filename: &str) -> &str {
+    let path = filename.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+/// Loads the sheet `tree` names, from the tree's own asset folder when it has one and from the
+/// bundled copies otherwise. Falls back to the bundled `fallback` sheet rather than failing.
+pub fn sheet_texture(
+    ctx: &egui::Context,
+    tree: &TreeExport,
+    sheet: &Sprite,
+    fallback: &str,
+) -> TextureId {
+    let name = sheet_file_name(&sheet.filename);
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(bytes) = tree
+        .asset_dir
+        .as_ref()
+        .and_then(|dir| std::fs::read(dir.join(name)).ok())
+    {
+        return image_as_texture(ctx, format!("bytes://{}/{name}", tree.tree), bytes);
     }
+
+    let bundled = |name: &str| BUNDLED_SHEETS.iter().find(|(bundled, _)| *bundled == name);
+    let (name, bytes) = bundled(name)
+        .or_else(|| {
+            log::warn!(
+                "Tree {} uses sprite sheet {name:?} which isn't available, using {fallback}",
+                tree.tree
+            );
+            bundled(fallback)
+        })
+        .expect("fallback sprite sheets are bundled");
+    image_as_texture(ctx, format!("bytes://{name}"), *bytes)
 }
 
-pub fn image_as_texture(ctx: &egui::Context, uri: String, bytes: &'static [u8]) -> TextureId {
+pub fn image_as_texture(
+    ctx: &egui::Context,
+    uri: String,
+    bytes: impl Into<egui::load::Bytes>,
+) -> TextureId {
     let texture_options = TextureOptions {
         magnification: egui::TextureFilter::Nearest,
         minification: egui::TextureFilter::Nearest,
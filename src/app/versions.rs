@@ -0,0 +1,57 @@
+use crate::{cluster, migration, tree::TreeExport, TemplateApp};
+
+impl TemplateApp {
+    /// Makes `tree` selectable alongside the active one. A second tree with the same version
+    /// replaces the first, even when the first is the active tree.
+    pub fn register_tree(&mut self, ctx: &egui::Context, tree: TreeExport) {
+        if tree.tree == self.tree.tree {
+            log::warn!("Replacing the active tree {:?}", self.tree.tree);
+            self.tree = tree;
+            self.load_textures(ctx);
+            self.stat_cache.invalidate();
+            self.regenerate_clusters();
+            return;
+        }
+        if let Some(previous) = self.trees.insert(tree.tree.clone(), tree) {
+            log::warn!("Replacing previously loaded tree {:?}", previous.tree);
+        }
+    }
+
+    /// Every loaded tree version, sorted.
+    pub fn tree_versions(&self) -> Vec<String> {
+        let mut versions: Vec<String> = self.trees.keys().cloned().collect();
+        versions.push(self.tree.tree.clone());
+        versions.sort_unstable();
+        versions
+    }
+
+    /// Activates another loaded tree and migrates the build onto it. Anything that needs the
+    /// player's attention is left in `migration_report`. Returns false if `version` isn't loaded.
+    pub fn switch_tree(&mut self, ctx: &egui::Context, version: &str) -> bool {
+        let Some(tree) = self.trees.remove(version) else {
+            return false;
+        };
+        let previous = std::mem::replace(&mut self.tree, tree);
+        self.load_textures(ctx);
+        // Cluster ids only depend on the socket, so the build's subtrees line up on both trees
+        // while its nodes are matched up.
+        cluster::generate(&mut self.tree.graph, &self.build.jewels);
+
+        let (build, mut report) = migration::migrate(
+            &self.build,
+            self.selected_class.id() as usize,
+            self.selected_ascendancy().map(|a| a.id.as_str()),
+            &previous,
+            &self.tree,
+        );
+        self.build = build;
+        // Sockets that lost or moved their jewel in the migration lose their subtree too.
+        self.regenerate_clusters();
+        report
+            .disconnected
+            .retain(|&skill| self.build.is_allocated(skill));
+        self.migration_report = (!report.is_clean()).then_some(report);
+        self.trees.insert(previous.tree.clone(), previous);
+        true
+    }
+}
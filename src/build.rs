@@ -0,0 +1,316 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{graph::TreeGraph, jewel::Jewel, pathing};
+
+/// Points available for the ascendancy tree, from the four labyrinths.
+pub const ASCENDANCY_POINTS: usize = 8;
+
+pub const MAX_LEVEL: u8 = 100;
+
+/// Labyrinths that award ascendancy points, two each, in the order they're unlocked.
+pub const LABYRINTHS: [&str; 4] = ["Normal", "Cruel", "Merciless", "Eternal"];
+
+pub struct QuestReward {
+    pub act: u8,
+    pub name: &'static str,
+    pub points: usize,
+}
+
+/// Every quest that awards passive points, with killing all bandits counted as a quest.
+pub const QUEST_REWARDS: [QuestReward; 22] = [
+    quest(1, "The Dweller of the Deep", 1),
+    quest(1, "The Marooned Mariner", 1),
+    quest(2, "The Way Forward", 1),
+    quest(2, "Deal with the Bandits (kill all)", 2),
+    quest(3, "Victario's Secrets", 1),
+    quest(3, "Piety's Pets", 1),
+    quest(4, "An Indomitable Spirit", 1),
+    quest(5, "In Service to Science", 1),
+    quest(5, "Kitava's Torments", 1),
+    quest(6, "The Father of War", 1),
+    quest(6, "The Puppet Mistress", 1),
+    quest(6, "The Cloven One", 1),
+    quest(7, "The Master of a Million Faces", 1),
+    quest(7, "Queen of Despair", 1),
+    quest(7, "Kishara's Star", 1),
+    quest(8, "Love is Dead", 1),
+    quest(8, "Reflection of Terror", 1),
+    quest(8, "The Gemling Legion", 1),
+    quest(9, "Queen of the Sands", 1),
+    quest(9, "The Ruler of Highgate", 1),
+    quest(10, "Vilenta's Vengeance", 1),
+    quest(10, "An End to Hunger", 2),
+];
+
+const fn quest(act: u8, name: &'static str, points: usize) -> QuestReward {
+    QuestReward { act, name, points }
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    UnknownNode(usize),
+    Disconnected(Vec<usize>),
+    /// Points allocated and points the progress allows.
+    TooManyAscendancyPoints(usize, usize),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNode(skill) => write!(f, "node {skill} does not exist in this tree"),
+            Self::Disconnected(skills) => write!(
+                f,
+                "{} allocated node(s) are not connected to the class or ascendancy start",
+                skills.len()
+            ),
+            Self::TooManyAscendancyPoints(used, available) => write!(
+                f,
+                "{used} ascendancy points allocated, only {available} are available"
+            ),
+        }
+    }
+}
+
+/// How far the character has got, which decides how many points the build may spend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Progress {
+    pub level: u8,
+    /// Names of the `QUEST_REWARDS` not yet completed, so new quests count as done.
+    pub missed_quests: BTreeSet<String>,
+    /// How many of the `LABYRINTHS` are completed.
+    pub labyrinths: u8,
+}
+
+impl Default for Progress {
+    /// A finished character, so plans aren't limited until a level is entered.
+    fn default() -> Self {
+        Self {
+            level: MAX_LEVEL,
+            missed_quests: BTreeSet::new(),
+            labyrinths: LABYRINTHS.len() as u8,
+        }
+    }
+}
+
+impl Progress {
+    pub fn quest_points(&self) -> usize {
+        QUEST_REWARDS
+            .iter()
+            .filter(|quest| !self.missed_quests.contains(quest.name))
+            .map(|quest| quest.points)
+            .sum()
+    }
+
+    /// One point per level after the first, plus quest rewards.
+    pub fn passive_points(&self) -> usize {
+        self.level.clamp(1, MAX_LEVEL) as usize - 1 + self.quest_points()
+    }
+
+    pub fn ascendancy_points(&self) -> usize {
+        (self.labyrinths as usize * 2).min(ASCENDANCY_POINTS)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Build {
+    /// The `TreeExport::tree` version this build was planned on.
+    #[serde(default)]
+    pub tree_version: String,
+    /// Allocated passives, keyed by `Node::skill`.
+    pub allocated: HashSet<usize>,
+    /// Position of the ascendancy within its class, 0 when none is chosen.
+    #[serde(default)]
+    pub ascendancy: u8,
+    /// Chosen effect for each allocated mastery, both keyed by their ids in the export.
+    #[serde(default)]
+    pub mastery_effects: HashMap<usize, usize>,
+    #[serde(default)]
+    pub progress: Progress,
+    /// Socketed jewels keyed by their socket's `Node::skill`. They stay in place when the socket
+    /// is refunded but only count while it's allocated.
+    #[serde(default)]
+    pub jewels: HashMap<usize, Jewel>,
+}
+
+impl Build {
+    pub fn is_allocated(&self, skill: usize) -> bool {
+        self.allocated.contains(&skill)
+    }
+
+    pub fn allocate_path(&mut self, path: &[usize]) {
+        self.allocated.extend(path);
+    }
+
+    pub fn refund(&mut self, skills: &HashSet<usize>) {
+        self.allocated.retain(|skill| !skills.contains(skill));
+        self.mastery_effects
+            .retain(|skill, _| !skills.contains(skill));
+    }
+
+    /// Allocated main tree nodes, masteries included.
+    pub fn passive_points_used(&self, graph: &TreeGraph) -> usize {
+        self.allocated.len() - self.ascendancy_points_used(graph)
+    }
+
+    /// Allocated ascendancy nodes, which spend ascendancy points instead of passive points.
+    pub fn ascendancy_points_used(&self, graph: &TreeGraph) -> usize {
+        self.allocated
+            .iter()
+            .filter_map(|&skill| graph.node(skill))
+            .filter(|node| node.ascendancy_name.is_some())
+            .count()
+    }
+
+    /// A mastery can be taken once anything else in its group is allocated.
+    pub fn can_take_mastery(&self, graph: &TreeGraph, mastery: usize) -> bool {
+        graph
+            .index_of(mastery)
+            .and_then(|index| graph.group_of(index))
+            .is_some_and(|group| {
+                graph.group_nodes[group].iter().any(|&other| {
+                    !graph.nodes[other].is_mastery && self.is_allocated(graph.skill(other))
+                })
+            })
+    }
+
+    /// Each effect can only be taken on one of the masteries sharing its name.
+    pub fn is_effect_taken_elsewhere(
+        &self,
+        graph: &TreeGraph,
+        mastery: usize,
+        effect: usize,
+    ) -> bool {
+        let name = graph.node(mastery).and_then(|node| node.name.as_deref());
+        self.mastery_effects.iter().any(|(&other, &taken)| {
+            other != mastery
+                && taken == effect
+                && graph.node(other).and_then(|node| node.name.as_deref()) == name
+        })
+    }
+
+    /// Allocates `mastery` with `effect`, replacing whatever effect it had.
+    pub fn choose_mastery_effect(&mut self, mastery: usize, effect: usize) {
+        self.allocated.insert(mastery);
+        self.mastery_effects.insert(mastery, effect);
+    }
+
+    /// Rejects builds that reference unknown nodes, overspend ascendancy points or allocate
+    /// islands the class and ascendancy can't reach.
+    pub fn validate(&self, graph: &TreeGraph, starts: &[usize]) -> Result<(), BuildError> {
+        if let Some(&unknown) = self
+            .allocated
+            .iter()
+            .find(|&&skill| graph.node(skill).is_none())
+        {
+            return Err(BuildError::UnknownNode(unknown));
+        }
+
+        let used = self.ascendancy_points_used(graph);
+        let available = self.progress.ascendancy_points();
+        if used > available {
+            return Err(BuildError::TooManyAscendancyPoints(used, available));
+        }
+
+        let connected = pathing::connected_to_start(graph, &self.allocated, starts);
+        let mut disconnected: Vec<usize> = self.allocated.difference(&connected).copied().collect();
+        if disconnected.is_empty() {
+            Ok(())
+        } else {
+            disconnected.sort_unstable();
+            Err(BuildError::Disconnected(disconnected))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_character_has_every_point() {
+        let progress = Progress::default();
+        assert_eq!(progress.quest_points(), 24);
+        assert_eq!(progress.passive_points(), 123);
+        assert_eq!(progress.ascendancy_points(), 8);
+    }
+
+    #[test]
+    fn missed_quests_cost_their_reward() {
+        let mut progress = Progress::default();
+        progress
+            .missed_quests
+            .insert("Deal with the Bandits (kill all)".to_string());
+        assert_eq!(progress.passive_points(), 121);
+        progress
+            .missed_quests
+            .insert("The Dweller of the Deep".to_string());
+        assert_eq!(progress.passive_points(), 120);
+        // Names no longer in the table don't take anything away.
+        progress.missed_quests.insert("Old Quest".to_string());
+        assert_eq!(progress.passive_points(), 120);
+    }
+
+    #[test]
+    fn levels_and_labyrinths_are_clamped() {
+        let progress = |level, labyrinths| Progress {
+            level,
+            labyrinths,
+            missed_quests: QUEST_REWARDS
+                .iter()
+                .map(|quest| quest.name.to_string())
+                .collect(),
+        };
+        assert_eq!(progress(1, 0).passive_points(), 0);
+        assert_eq!(progress(0, 0).passive_points(), 0);
+        assert_eq!(progress(70, 0).passive_points(), 69);
+        assert_eq!(progress(255, 0).passive_points(), 99);
+        assert_eq!(progress(1, 0).ascendancy_points(), 0);
+        assert_eq!(progress(1, 1).ascendancy_points(), 2);
+        assert_eq!(progress(1, 3).ascendancy_points(), 6);
+        assert_eq!(progress(1, 4).ascendancy_points(), 8);
+        assert_eq!(progress(1, 9).ascendancy_points(), 8);
+    }
+
+    #[test]
+    fn ascendancy_budget_follows_labyrinths() {
+        let node = |skill: usize, out: &[usize]| {
+            let node = crate::tree::Node {
+                skill: Some(skill),
+                ascendancy_name: Some("Juggernaut".to_string()),
+                is_ascendancy_start: skill == 200,
+                out: out.iter().map(|id| id.to_string()).collect(),
+                ..Default::default()
+            };
+            (skill.to_string(), node)
+        };
+        let graph = TreeGraph::new(
+            [
+                node(200, &[201]),
+                node(201, &[202]),
+                node(202, &[203]),
+                node(203, &[]),
+            ]
+            .into_iter()
+            .collect(),
+            HashMap::new(),
+        );
+        let mut build = Build {
+            allocated: HashSet::from([201, 202, 203]),
+            ..Default::default()
+        };
+        assert_eq!(build.ascendancy_points_used(&graph), 3);
+        assert!(build.validate(&graph, &[200]).is_ok());
+
+        build.progress.labyrinths = 1;
+        assert!(matches!(
+            build.validate(&graph, &[200]),
+            Err(BuildError::TooManyAscendancyPoints(3, 2))
+        ));
+    }
+}
@@ -31,6 +31,12 @@ impl Camera {
         self.pan.y += (after_zoom_world.y - before_zoom_world.y) * self.zoom;
     }
 
+    /// Pans so that `world` ends up at `screen`, keeping the zoom.
+    pub fn center_on(&mut self, world: Pos2, screen: Pos2) {
+        self.pan.x = screen.x - self.zoom_center.x - (world.x - self.zoom_center.x) * self.zoom;
+        self.pan.y = screen.y - self.zoom_center.y - (world.y - self.zoom_center.y) * self.zoom;
+    }
+
     pub fn world_to_screen(&self, world: Pos2) -> Pos2 {
         let screen_x = self.zoom_center.x + (world.x - self.zoom_center.x) * self.zoom + self.pan.x;
         let screen_y = self.zoom_center.y + (world.y - self.zoom_center.y) * self.zoom + self.pan.y;
@@ -43,6 +49,14 @@ impl Camera {
         pos2(world_x, world_y)
     }
 
+    /// The part of the tree that `screen` shows, in tree coordinates.
+    pub fn visible_world(&self, screen: Rect) -> Rect {
+        Rect::from_min_max(
+            self.screen_to_world(screen.min),
+            self.screen_to_world(screen.max),
+        )
+    }
+
     pub fn rect_with_size(&self, pos: Pos2, width: f32, height: f32) -> Rect {
         Rect::from_min_max(
             self.world_to_screen(pos),
@@ -0,0 +1,640 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    sync::OnceLock,
+};
+
+use regex::Regex;
+
+use crate::{
+    graph::TreeGraph,
+    jewel::Jewel,
+    tree::{ExpansionJewel, Node, ORBIT_NODES},
+};
+
+/// Generated node ids start here, clear of every id the export uses. Tree URLs list them
+/// separately, offset by this much.
+pub const GENERATED_ID_BASE: usize = 0x10000;
+
+/// Where on a 16 node orbit each of the 12 evenly spaced cluster slots falls.
+const SLOTS_ON_16: [usize; 12] = [0, 1, 3, 4, 5, 7, 8, 9, 11, 12, 13, 15];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClusterSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl ClusterSize {
+    /// The `size` an `ExpansionJewel` socket needs to hold this cluster.
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Small => 0,
+            Self::Medium => 1,
+            Self::Large => 2,
+        }
+    }
+
+    /// How many passives the jewel can add, inclusive.
+    pub fn passives(&self) -> (usize, usize) {
+        match self {
+            Self::Small => (2, 3),
+            Self::Medium => (4, 6),
+            Self::Large => (8, 12),
+        }
+    }
+
+    pub fn max_sockets(&self) -> usize {
+        match self {
+            Self::Small => 0,
+            Self::Medium => 1,
+            Self::Large => 2,
+        }
+    }
+
+    /// Evenly spaced positions around the proxy's orbit that nodes can take.
+    fn slots(&self) -> usize {
+        match self {
+            Self::Small => 6,
+            Self::Medium | Self::Large => 12,
+        }
+    }
+
+    /// Slots small passives fill, in order, skipping anything already taken.
+    fn small_slots(&self) -> &'static [usize] {
+        match self {
+            Self::Small => &[0, 4, 2],
+            Self::Medium => &[0, 6, 8, 4, 10, 2],
+            Self::Large => &[0, 4, 6, 8, 10, 2, 7, 5, 9, 3, 11, 1],
+        }
+    }
+
+    fn notable_slots(&self) -> &'static [usize] {
+        match self {
+            Self::Small => &[4],
+            Self::Medium => &[6, 10, 2, 0],
+            Self::Large => &[6, 4, 8, 10, 2],
+        }
+    }
+
+    fn socket_slots(&self, sockets: usize) -> &'static [usize] {
+        match (self, sockets) {
+            (Self::Large, 1) | (Self::Medium, 1) => &[6],
+            (Self::Large, 2) => &[4, 8],
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for ClusterSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Small => "Small",
+            Self::Medium => "Medium",
+            Self::Large => "Large",
+        })
+    }
+}
+
+struct Patterns {
+    passives: Regex,
+    sockets: Regex,
+    notable: Regex,
+    small: Regex,
+}
+
+// Compiled once on first use, like the modifier patterns.
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let regex = |pattern: &str| Regex::new(pattern).expect("cluster patterns are valid");
+        Patterns {
+            passives: regex(r"^Adds (\d+) Passive Skills?$"),
+            sockets: regex(
+                r"^(\d+) Added Passive Skills? (?:is a Jewel Socket|are Jewel Sockets)$",
+            ),
+            notable: regex(r"^1 Added Passive Skill is (.+)$"),
+            small: regex(r"^Added Small Passive Skills (?:also )?grant: (.+)$"),
+        }
+    })
+}
+
+/// What a cluster jewel adds to the tree, read from the same mod lines the game shows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterJewel {
+    pub size: ClusterSize,
+    /// Every added passive, sockets and notables included.
+    pub passives: usize,
+    pub sockets: usize,
+    /// Notable names. The export doesn't carry their stats, so they're added without any.
+    pub notables: Vec<String>,
+    /// Stats every small passive grants.
+    pub small_stats: Vec<String>,
+}
+
+impl ClusterJewel {
+    /// `None` unless the jewel has a cluster base. Counts the mods leave out fall back to the
+    /// fewest the size can roll.
+    pub fn from_jewel(jewel: &Jewel) -> Option<Self> {
+        let size = jewel.base.cluster_size()?;
+        let (min, max) = size.passives();
+        let mut cluster = Self {
+            size,
+            passives: min,
+            sockets: 0,
+            notables: vec![],
+            small_stats: vec![],
+        };
+        let patterns = patterns();
+        for line in &jewel.mods {
+            if let Some(captures) = patterns.passives.captures(line) {
+                cluster.passives = captures[1].parse().unwrap_or(min).clamp(min, max);
+            } else if let Some(captures) = patterns.sockets.captures(line) {
+                cluster.sockets = captures[1].parse().unwrap_or(0);
+            } else if let Some(captures) = patterns.notable.captures(line) {
+                cluster.notables.push(captures[1].to_string());
+            } else if let Some(captures) = patterns.small.captures(line) {
+                cluster.small_stats.push(captures[1].to_string());
+            }
+        }
+        cluster.sockets = cluster.sockets.min(size.max_sockets());
+        Some(cluster)
+    }
+
+    /// Whether a socket expanding to `expansion` can hold this cluster.
+    pub fn fits(&self, expansion: Option<&ExpansionJewel>) -> bool {
+        expansion.is_some_and(|expansion| self.size.index() <= expansion.size)
+    }
+
+    /// Slot of every added node. Sockets go first, then notables and small passives fill the
+    /// slots left in their own order.
+    fn layout(&self) -> BTreeMap<usize, Slot<'_>> {
+        let mut slots = BTreeMap::new();
+        for &slot in self.size.socket_slots(self.sockets) {
+            slots.insert(slot, Slot::Socket);
+        }
+        let mut remaining = self.passives.saturating_sub(self.sockets);
+        let notables = free_slots(&slots, self.size.notable_slots(), remaining);
+        for (slot, name) in notables.into_iter().zip(&self.notables) {
+            slots.insert(slot, Slot::Notable(name));
+            remaining -= 1;
+        }
+        for slot in free_slots(&slots, self.size.small_slots(), remaining) {
+            slots.insert(slot, Slot::Small);
+        }
+        slots
+    }
+}
+
+/// The first `count` slots of `order` nothing has taken yet.
+fn free_slots(taken: &BTreeMap<usize, Slot<'_>>, order: &[usize], count: usize) -> Vec<usize> {
+    order
+        .iter()
+        .copied()
+        .filter(|slot| !taken.contains_key(slot))
+        .take(count)
+        .collect()
+}
+
+enum Slot<'a> {
+    Socket,
+    Notable(&'a str),
+    Small,
+}
+
+/// Rebuilds every cluster subtree from `jewels`, replacing whatever was generated before.
+/// Nested sockets only expand once the cluster around them uses them.
+pub fn generate(graph: &mut TreeGraph, jewels: &HashMap<usize, Jewel>) {
+    graph.clear_generated();
+
+    let mut clusters: Vec<(usize, ClusterJewel)> = jewels
+        .iter()
+        .filter_map(|(&socket, jewel)| Some((socket, ClusterJewel::from_jewel(jewel)?)))
+        .collect();
+    // Parents before the sockets nested in them.
+    clusters.sort_by_key(|(socket, cluster)| (std::cmp::Reverse(cluster.size), *socket));
+
+    let mut nested = HashSet::new();
+    for (socket, cluster) in clusters {
+        let Some(expansion) = graph
+            .node(socket)
+            .and_then(|node| node.expansion_jewel.clone())
+        else {
+            continue;
+        };
+        if !cluster.fits(Some(&expansion))
+            || (expansion.parent.is_some() && !nested.contains(&socket))
+        {
+            continue;
+        }
+        nested.extend(generate_subtree(graph, socket, &expansion, &cluster));
+    }
+}
+
+/// Lays `cluster` out around the proxy of `socket` and links it in. Returns the nested
+/// sockets it used.
+fn generate_subtree(
+    graph: &mut TreeGraph,
+    socket: usize,
+    expansion: &ExpansionJewel,
+    cluster: &ClusterJewel,
+) -> Vec<usize> {
+    let Some(proxy) = expansion
+        .proxy
+        .parse()
+        .ok()
+        .and_then(|proxy| graph.index_of(proxy))
+    else {
+        log::warn!("Jewel socket {socket} has no proxy node, skipping its cluster");
+        return vec![];
+    };
+    let (Some(group), Some(orbit)) = (graph.group_of(proxy), graph.nodes[proxy].orbit) else {
+        return vec![];
+    };
+    let on_orbit = ORBIT_NODES[orbit] as usize;
+    let start = graph.nodes[proxy].orbit_index.unwrap_or(0);
+    let slots = cluster.size.slots();
+    let orbit_index = |slot: usize| {
+        let offset = match (slots, on_orbit) {
+            (12, 16) => SLOTS_ON_16[slot],
+            _ => slot * on_orbit / slots,
+        };
+        (start + offset) % on_orbit
+    };
+    let id_base = GENERATED_ID_BASE + (cluster.size.index() << 4) + socket_bits(graph, expansion);
+
+    let mut ring = vec![];
+    let mut sockets = vec![];
+    for (slot, kind) in cluster.layout() {
+        let orbit_index = orbit_index(slot);
+        let index = match kind {
+            Slot::Socket => {
+                // Nested sockets are in the export already, sitting on the proxy's orbit.
+                let found = graph.group_nodes[group].iter().copied().find(|&other| {
+                    let node = &graph.nodes[other];
+                    node.is_jewel_socket
+                        && node.orbit == Some(orbit)
+                        && node.orbit_index == Some(orbit_index)
+                });
+                let Some(found) = found else {
+                    continue;
+                };
+                sockets.push(graph.skill(found));
+                found
+            }
+            Slot::Notable(name) => graph.push_generated(
+                Node {
+                    skill: Some(id_base + slot),
+                    name: Some(name.to_string()),
+                    is_notable: Some(true),
+                    group: Some(group),
+                    orbit: Some(orbit),
+                    orbit_index: Some(orbit_index),
+                    ..Default::default()
+                },
+                group,
+            ),
+            Slot::Small => graph.push_generated(
+                Node {
+                    skill: Some(id_base + slot),
+                    name: Some("Small Passive".to_string()),
+                    group: Some(group),
+                    orbit: Some(orbit),
+                    orbit_index: Some(orbit_index),
+                    stats: cluster.small_stats.clone(),
+                    ..Default::default()
+                },
+                group,
+            ),
+        };
+        ring.push(index);
+    }
+
+    // Slots link in order without wrapping around; the lowest one is the entrance.
+    if let (Some(&entrance), Some(socket)) = (ring.first(), graph.index_of(socket)) {
+        graph.link(socket, entrance);
+    }
+    for pair in ring.windows(2) {
+        graph.link(pair[0], pair[1]);
+    }
+    sockets
+}
+
+/// Folds the socket's place among its parents into the generated ids, so every subtree gets
+/// its own range and ids stay the same each time they're generated.
+fn socket_bits(graph: &TreeGraph, expansion: &ExpansionJewel) -> usize {
+    let mut bits = 0;
+    let mut current = Some(expansion.clone());
+    while let Some(expansion) = current {
+        let shift = match expansion.size {
+            2 => 6,
+            1 => 9,
+            _ => 11,
+        };
+        bits += expansion.index << shift;
+        current = expansion
+            .parent
+            .and_then(|parent| parent.parse().ok())
+            .and_then(|parent| graph.node(parent))
+            .and_then(|node| node.expansion_jewel.clone());
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+    use crate::{
+        jewel::JewelBase,
+        tree::{Group, ORBIT_RADII},
+    };
+
+    fn jewel(base: JewelBase, mods: &[&str]) -> Jewel {
+        let mut jewel = Jewel::new(base);
+        jewel.mods = mods.iter().map(|line| line.to_string()).collect();
+        jewel
+    }
+
+    fn large() -> Jewel {
+        jewel(
+            JewelBase::LargeCluster,
+            &[
+                "Adds 8 Passive Skills",
+                "2 Added Passive Skills are Jewel Sockets",
+                "1 Added Passive Skill is Feed the Fury",
+                "Added Small Passive Skills grant: 10% increased Attack Damage",
+            ],
+        )
+    }
+
+    fn medium() -> Jewel {
+        jewel(
+            JewelBase::MediumCluster,
+            &[
+                "Adds 4 Passive Skills",
+                "1 Added Passive Skill is Vicious Skewering",
+                "Added Small Passive Skills grant: 12% increased Physical Damage",
+            ],
+        )
+    }
+
+    fn socket(skill: usize, group: usize, expansion: (usize, usize, usize, Option<usize>)) -> Node {
+        let (size, index, proxy, parent) = expansion;
+        Node {
+            skill: Some(skill),
+            group: Some(group),
+            orbit: Some(3),
+            orbit_index: Some(0),
+            is_jewel_socket: true,
+            expansion_jewel: Some(ExpansionJewel {
+                size,
+                index,
+                proxy: proxy.to_string(),
+                parent: parent.map(|parent| parent.to_string()),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn proxy(skill: usize, group: usize, orbit: usize) -> Node {
+        Node {
+            skill: Some(skill),
+            group: Some(group),
+            orbit: Some(orbit),
+            orbit_index: Some(0),
+            is_proxy: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// A large socket (100) whose proxy (200) sits in group 2, with the two medium sockets a
+    /// large cluster can use (300, 301) on the proxy's orbit, each with its own proxy.
+    fn graph() -> TreeGraph {
+        let mut medium_left = socket(300, 2, (1, 0, 400, Some(100)));
+        medium_left.orbit_index = Some(SLOTS_ON_16[4]);
+        let mut medium_right = socket(301, 2, (1, 1, 401, Some(100)));
+        medium_right.orbit_index = Some(SLOTS_ON_16[8]);
+        let nodes = [
+            socket(100, 1, (2, 0, 200, None)),
+            proxy(200, 2, 3),
+            medium_left,
+            medium_right,
+            proxy(400, 3, 2),
+            proxy(401, 4, 2),
+        ];
+        let group = |x: f32, y: f32| Group {
+            x,
+            y,
+            orbits: vec![0, 2, 3],
+            ..Default::default()
+        };
+        let groups = [
+            group(0.0, 0.0),
+            group(1000.0, 0.0),
+            group(2000.0, 0.0),
+            group(2000.0, 1000.0),
+        ];
+        TreeGraph::new(
+            nodes
+                .into_iter()
+                .map(|node| (node.skill.unwrap().to_string(), node))
+                .collect(),
+            groups
+                .into_iter()
+                .enumerate()
+                .map(|(index, group)| ((index + 1).to_string(), group))
+                .collect(),
+        )
+    }
+
+    /// Skill id and orbit index of every generated node in `group`, in id order.
+    fn generated_in(graph: &TreeGraph, group: usize) -> Vec<(usize, usize)> {
+        let mut nodes: Vec<(usize, usize)> = (0..graph.nodes.len())
+            .filter(|&index| graph.is_generated(index) && graph.group_of(index) == Some(group))
+            .map(|index| (graph.skill(index), graph.nodes[index].orbit_index.unwrap()))
+            .collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    fn neighbours(graph: &TreeGraph, skill: usize) -> Vec<usize> {
+        let mut skills: Vec<usize> = graph.adjacency[graph.index_of(skill).unwrap()]
+            .iter()
+            .map(|&index| graph.skill(index))
+            .collect();
+        skills.sort_unstable();
+        skills
+    }
+
+    #[test]
+    fn reads_mod_lines() {
+        let cluster = ClusterJewel::from_jewel(&large()).unwrap();
+        assert_eq!(cluster.size, ClusterSize::Large);
+        assert_eq!(cluster.passives, 8);
+        assert_eq!(cluster.sockets, 2);
+        assert_eq!(cluster.notables, ["Feed the Fury"]);
+        assert_eq!(cluster.small_stats, ["10% increased Attack Damage"]);
+
+        // Out of range counts are clamped to what the size can roll.
+        let odd = jewel(
+            JewelBase::SmallCluster,
+            &[
+                "Adds 9 Passive Skills",
+                "1 Added Passive Skill is a Jewel Socket",
+                "Added Small Passive Skills also grant: +5 to Strength",
+            ],
+        );
+        let cluster = ClusterJewel::from_jewel(&odd).unwrap();
+        assert_eq!((cluster.passives, cluster.sockets), (3, 0));
+        assert_eq!(cluster.small_stats, ["+5 to Strength"]);
+
+        let bare = ClusterJewel::from_jewel(&Jewel::new(JewelBase::MediumCluster)).unwrap();
+        assert_eq!((bare.passives, bare.sockets), (4, 0));
+        assert!(ClusterJewel::from_jewel(&Jewel::new(JewelBase::Viridian)).is_none());
+    }
+
+    #[test]
+    fn fits_sockets_its_size_or_larger() {
+        let small = ClusterJewel::from_jewel(&Jewel::new(JewelBase::SmallCluster)).unwrap();
+        let large = ClusterJewel::from_jewel(&large()).unwrap();
+        let expansion = |size| ExpansionJewel {
+            size,
+            index: 0,
+            proxy: String::new(),
+            parent: None,
+        };
+        assert!(small.fits(Some(&expansion(0))));
+        assert!(small.fits(Some(&expansion(2))));
+        assert!(!large.fits(Some(&expansion(1))));
+        assert!(!large.fits(None));
+    }
+
+    #[test]
+    fn lays_out_a_large_cluster() {
+        let mut graph = graph();
+        let jewels = HashMap::from([(100, large())]);
+        generate(&mut graph, &jewels);
+
+        // Ids follow Path of Building's: 0x10000, the size index shifted by 4, then the slot.
+        let id = |slot: usize| 0x10000 + (2 << 4) + slot;
+        assert_eq!(
+            generated_in(&graph, 1),
+            [
+                (id(0), SLOTS_ON_16[0]),
+                (id(2), SLOTS_ON_16[2]),
+                (id(5), SLOTS_ON_16[5]),
+                (id(6), SLOTS_ON_16[6]),
+                (id(7), SLOTS_ON_16[7]),
+                (id(10), SLOTS_ON_16[10]),
+            ]
+        );
+        let notable = graph.node(id(6)).unwrap();
+        assert_eq!(notable.name.as_deref(), Some("Feed the Fury"));
+        assert_eq!(notable.is_notable, Some(true));
+        let small = graph.node(id(0)).unwrap();
+        assert_eq!(small.stats, ["10% increased Attack Damage"]);
+
+        // Slot 0 sits straight above the proxy group's center.
+        let entrance = graph.index_of(id(0)).unwrap();
+        assert_eq!(
+            graph.positions[entrance],
+            pos2(1000.0, -(ORBIT_RADII[3] as f32))
+        );
+
+        // The socket leads into slot 0 and the ring runs through the nested sockets in order.
+        assert_eq!(neighbours(&graph, 100), [id(0)]);
+        assert_eq!(neighbours(&graph, id(2)), [300, id(0)]);
+        assert_eq!(neighbours(&graph, 300), [id(2), id(5)]);
+        assert_eq!(neighbours(&graph, 301), [id(7), id(10)]);
+        assert_eq!(neighbours(&graph, id(10)), [301]);
+    }
+
+    #[test]
+    fn nests_medium_clusters_in_used_sockets() {
+        let mut graph = graph();
+        let jewels = HashMap::from([(100, large()), (301, medium())]);
+        generate(&mut graph, &jewels);
+
+        // The second medium socket adds its index shifted by 9.
+        let id = |slot: usize| 0x10000 + (1 << 4) + (1 << 9) + slot;
+        assert_eq!(
+            generated_in(&graph, 3),
+            [
+                (id(0), SLOTS_ON_16[0]),
+                (id(4), SLOTS_ON_16[4]),
+                (id(6), SLOTS_ON_16[6]),
+                (id(8), SLOTS_ON_16[8]),
+            ]
+        );
+        assert_eq!(
+            graph.node(id(6)).unwrap().name.as_deref(),
+            Some("Vicious Skewering")
+        );
+        assert!(neighbours(&graph, 301).contains(&id(0)));
+        assert!(generated_in(&graph, 2).is_empty());
+    }
+
+    #[test]
+    fn lays_out_a_small_cluster() {
+        let mut graph = graph();
+        let small = jewel(
+            JewelBase::SmallCluster,
+            &[
+                "Adds 3 Passive Skills",
+                "1 Added Passive Skill is Fan of Blades",
+            ],
+        );
+        generate(&mut graph, &HashMap::from([(100, small)]));
+
+        // Six slots spread over the 16 node orbit.
+        let id = |slot: usize| 0x10000 + slot;
+        assert_eq!(
+            generated_in(&graph, 1),
+            [(id(0), 0), (id(2), 5), (id(4), 10)]
+        );
+        assert_eq!(
+            graph.node(id(4)).unwrap().name.as_deref(),
+            Some("Fan of Blades")
+        );
+    }
+
+    #[test]
+    fn skips_nested_sockets_without_a_parent_cluster() {
+        let mut graph = graph();
+        let base = graph.nodes.len();
+        generate(&mut graph, &HashMap::from([(301, medium())]));
+        assert_eq!(graph.nodes.len(), base);
+    }
+
+    #[test]
+    fn regenerating_leaves_nothing_stale() {
+        let mut graph = graph();
+        let base = graph.nodes.len();
+        let base_adjacency = graph.adjacency.clone();
+        let mut jewels = HashMap::from([(100, large()), (300, medium()), (301, medium())]);
+        generate(&mut graph, &jewels);
+        let generated = graph.nodes.len();
+        assert_eq!(generated, base + 6 + 4 + 4);
+
+        generate(&mut graph, &jewels);
+        assert_eq!(graph.nodes.len(), generated);
+
+        // Without the large cluster its nested sockets aren't reachable, so nothing is left.
+        jewels.remove(&100);
+        generate(&mut graph, &jewels);
+        assert_eq!(graph.nodes.len(), base);
+        assert_eq!(graph.adjacency, base_adjacency);
+        assert!(graph
+            .group_nodes
+            .iter()
+            .flatten()
+            .all(|&index| index < base));
+        assert!(graph.index_of(0x10000 + (2 << 4)).is_none());
+
+        graph.clear_generated();
+        assert_eq!(graph.adjacency, base_adjacency);
+    }
+}
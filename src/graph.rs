@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use egui::{pos2, Pos2};
+
+use crate::tree::{orbit_position, Group, Node};
+
+/// Dense index into the `TreeGraph` vectors. Only stable for one loaded tree; use `Node::skill`
+/// for anything that gets stored or shared.
+pub type NodeIndex = usize;
+
+/// The export compiled into flat vectors, built once when the tree is loaded.
+#[derive(Default)]
+pub struct TreeGraph {
+    pub nodes: Vec<Node>,
+    pub positions: Vec<Pos2>,
+    /// Undirected edges, with masteries, proxies and ascendancy boundaries already cut.
+    pub adjacency: Vec<Vec<NodeIndex>>,
+    pub groups: Vec<Group>,
+    pub group_nodes: Vec<Vec<NodeIndex>>,
+    node_groups: Vec<Option<usize>>,
+    by_skill: HashMap<usize, NodeIndex>,
+    /// Nodes from the export itself. Generated cluster jewel nodes are appended after them so
+    /// the export's indices never move.
+    base_len: usize,
+    /// Edges `link` added between two export nodes, such as a socket and a nested socket.
+    generated_links: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl TreeGraph {
+    pub fn new(nodes: HashMap<String, Node>, groups: HashMap<String, Group>) -> Self {
+        let mut groups: Vec<(usize, Group)> = groups
+            .into_iter()
+            .filter_map(|(id, group)| Some((id.parse().ok()?, group)))
+            .collect();
+        groups.sort_by_key(|(id, _)| *id);
+        let group_index: HashMap<usize, usize> = groups
+            .iter()
+            .enumerate()
+            .map(|(index, (id, _))| (*id, index))
+            .collect();
+        let groups: Vec<Group> = groups.into_iter().map(|(_, group)| group).collect();
+
+        // Nodes without a skill id (only the synthetic "root") never get drawn or allocated.
+        let mut nodes: Vec<Node> = nodes.into_values().filter(|n| n.skill.is_some()).collect();
+        nodes.sort_by_key(|node| node.skill);
+        let by_skill: HashMap<usize, NodeIndex> = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| Some((node.skill?, index)))
+            .collect();
+
+        let node_groups: Vec<Option<usize>> = nodes
+            .iter()
+            .map(|node| {
+                node.group
+                    .and_then(|group| group_index.get(&group).copied())
+            })
+            .collect();
+        let mut group_nodes = vec![vec![]; groups.len()];
+        for (index, group) in node_groups.iter().enumerate() {
+            if let Some(group) = group {
+                group_nodes[*group].push(index);
+            }
+        }
+        let positions = nodes
+            .iter()
+            .zip(&node_groups)
+            .map(|(node, group)| match group {
+                Some(group) => {
+                    let (x, y) = orbit_position(node, &groups[*group]);
+                    pos2(x, y)
+                }
+                None => pos2(0.0, 0.0),
+            })
+            .collect();
+
+        let mut adjacency = vec![vec![]; nodes.len()];
+        for (index, node) in nodes.iter().enumerate() {
+            for &other in node
+                .out
+                .iter()
+                .filter_map(|id| by_skill.get(&id.parse::<usize>().ok()?))
+            {
+                if node.can_connect(&nodes[other]) {
+                    adjacency[index].push(other);
+                    adjacency[other].push(index);
+                }
+            }
+        }
+        for neighbours in &mut adjacency {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+
+        Self {
+            base_len: nodes.len(),
+            generated_links: vec![],
+            nodes,
+            positions,
+            adjacency,
+            groups,
+            group_nodes,
+            node_groups,
+            by_skill,
+        }
+    }
+
+    /// Whether `index` was generated by a cluster jewel rather than read from the export.
+    pub fn is_generated(&self, index: NodeIndex) -> bool {
+        index >= self.base_len
+    }
+
+    /// Drops every generated node and the edges leading to them.
+    pub fn clear_generated(&mut self) {
+        let base_len = self.base_len;
+        for node in self.nodes.drain(base_len..) {
+            if let Some(skill) = node.skill {
+                self.by_skill.remove(&skill);
+            }
+        }
+        self.positions.truncate(base_len);
+        self.adjacency.truncate(base_len);
+        self.node_groups.truncate(base_len);
+        for neighbours in &mut self.adjacency {
+            neighbours.retain(|&other| other < base_len);
+        }
+        for members in &mut self.group_nodes {
+            members.retain(|&other| other < base_len);
+        }
+        for (a, b) in std::mem::take(&mut self.generated_links) {
+            self.adjacency[a].retain(|&other| other != b);
+            self.adjacency[b].retain(|&other| other != a);
+        }
+    }
+
+    /// Appends a generated node in `group`, placed on its orbit like any export node.
+    pub fn push_generated(&mut self, node: Node, group: usize) -> NodeIndex {
+        let index = self.nodes.len();
+        let (x, y) = orbit_position(&node, &self.groups[group]);
+        if let Some(skill) = node.skill {
+            self.by_skill.insert(skill, index);
+        }
+        self.nodes.push(node);
+        self.positions.push(pos2(x, y));
+        self.adjacency.push(vec![]);
+        self.node_groups.push(Some(group));
+        self.group_nodes[group].push(index);
+        index
+    }
+
+    /// Connects two nodes both ways, until the next `clear_generated`.
+    pub fn link(&mut self, a: NodeIndex, b: NodeIndex) {
+        if self.adjacency[a].contains(&b) {
+            return;
+        }
+        self.adjacency[a].push(b);
+        self.adjacency[b].push(a);
+        if !self.is_generated(a) && !self.is_generated(b) {
+            self.generated_links.push((a, b));
+        }
+    }
+
+    pub fn index_of(&self, skill: usize) -> Option<NodeIndex> {
+        self.by_skill.get(&skill).copied()
+    }
+
+    pub fn node(&self, skill: usize) -> Option<&Node> {
+        self.index_of(skill).map(|index| &self.nodes[index])
+    }
+
+    pub fn skill(&self, index: NodeIndex) -> usize {
+        self.nodes[index].skill.unwrap_or_default()
+    }
+
+    /// Index into `groups` and `group_nodes`.
+    pub fn group_of(&self, index: NodeIndex) -> Option<usize> {
+        self.node_groups[index]
+    }
+
+    /// Skill id of the start node for `CharacterClass::id`.
+    pub fn class_start(&self, class_index: usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| node.class_start_index == Some(class_index))
+            .and_then(|node| node.skill)
+    }
+
+    pub fn ascendancy_start(&self, ascendancy: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| {
+                node.is_ascendancy_start && node.ascendancy_name.as_deref() == Some(ascendancy)
+            })
+            .and_then(|node| node.skill)
+    }
+
+    /// The nodes a build grows from: its class start, and its ascendancy start once one is
+    /// chosen.
+    pub fn start_nodes(&self, class_index: usize, ascendancy: Option<&str>) -> Vec<usize> {
+        self.class_start(class_index)
+            .into_iter()
+            .chain(ascendancy.and_then(|name| self.ascendancy_start(name)))
+            .collect()
+    }
+}
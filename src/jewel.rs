@@ -0,0 +1,468 @@
+use std::{fmt, sync::OnceLock};
+
+use egui::Pos2;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cluster::ClusterSize,
+    graph::{NodeIndex, TreeGraph},
+    tree::Node,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JewelBase {
+    Crimson,
+    Viridian,
+    Cobalt,
+    Prismatic,
+    /// Any of the four "Eye Jewel" bases.
+    Abyss,
+    Timeless,
+    LargeCluster,
+    MediumCluster,
+    SmallCluster,
+}
+
+impl JewelBase {
+    pub const ALL: [JewelBase; 9] = [
+        Self::Crimson,
+        Self::Viridian,
+        Self::Cobalt,
+        Self::Prismatic,
+        Self::Abyss,
+        Self::Timeless,
+        Self::LargeCluster,
+        Self::MediumCluster,
+        Self::SmallCluster,
+    ];
+
+    /// The base type line as the game writes it.
+    pub fn base_name(&self) -> &'static str {
+        match self {
+            Self::Crimson => "Crimson Jewel",
+            Self::Viridian => "Viridian Jewel",
+            Self::Cobalt => "Cobalt Jewel",
+            Self::Prismatic => "Prismatic Jewel",
+            Self::Abyss => "Searching Eye Jewel",
+            Self::Timeless => "Timeless Jewel",
+            Self::LargeCluster => "Large Cluster Jewel",
+            Self::MediumCluster => "Medium Cluster Jewel",
+            Self::SmallCluster => "Small Cluster Jewel",
+        }
+    }
+
+    pub fn from_base_name(name: &str) -> Option<Self> {
+        if name.ends_with("Eye Jewel") {
+            return Some(Self::Abyss);
+        }
+        Self::ALL.into_iter().find(|base| base.base_name() == name)
+    }
+
+    /// The base inside a magic item's name, like "Vivid Cobalt Jewel of the Wolf".
+    pub fn from_magic_name(name: &str) -> Option<Self> {
+        if name.contains("Eye Jewel") {
+            return Some(Self::Abyss);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|base| name.contains(base.base_name()))
+    }
+
+    /// The art drawn inside a socket holding this jewel, from the export's `jewel` sheet.
+    pub fn socket_art(&self) -> &'static str {
+        match self {
+            Self::Crimson => "JewelSocketActiveRed",
+            Self::Viridian => "JewelSocketActiveGreen",
+            Self::Cobalt => "JewelSocketActiveBlue",
+            Self::Prismatic => "JewelSocketActivePrismatic",
+            Self::Abyss => "JewelSocketActiveAbyss",
+            Self::Timeless => "JewelSocketActiveLegion",
+            Self::LargeCluster => "JewelSocketActiveAltRed",
+            Self::MediumCluster => "JewelSocketActiveAltBlue",
+            Self::SmallCluster => "JewelSocketActiveAltPurple",
+        }
+    }
+
+    pub fn cluster_size(&self) -> Option<ClusterSize> {
+        match self {
+            Self::LargeCluster => Some(ClusterSize::Large),
+            Self::MediumCluster => Some(ClusterSize::Medium),
+            Self::SmallCluster => Some(ClusterSize::Small),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JewelBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.base_name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JewelRadius {
+    Small,
+    Medium,
+    Large,
+    VeryLarge,
+    Massive,
+}
+
+impl JewelRadius {
+    pub const ALL: [JewelRadius; 5] = [
+        Self::Small,
+        Self::Medium,
+        Self::Large,
+        Self::VeryLarge,
+        Self::Massive,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Small => "Small",
+            Self::Medium => "Medium",
+            Self::Large => "Large",
+            Self::VeryLarge => "Very Large",
+            Self::Massive => "Massive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|radius| radius.name() == name)
+    }
+
+    /// Distance from the socket, in tree units, that the radius reaches.
+    pub fn outer(&self) -> f32 {
+        match self {
+            Self::Small => 960.0,
+            Self::Medium => 1440.0,
+            Self::Large => 1800.0,
+            Self::VeryLarge => 2400.0,
+            Self::Massive => 2880.0,
+        }
+    }
+
+    /// The ring a Thread of Hope of this size affects. Rings don't line up with the radii.
+    pub fn ring(&self) -> Band {
+        let (inner, outer) = match self {
+            Self::Small => (960.0, 1320.0),
+            Self::Medium => (1320.0, 1680.0),
+            Self::Large => (1680.0, 2040.0),
+            Self::VeryLarge => (2040.0, 2400.0),
+            Self::Massive => (2400.0, 2880.0),
+        };
+        Band { inner, outer }
+    }
+}
+
+impl fmt::Display for JewelRadius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The distances from a socket, in tree units, at which passives are affected by its jewel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl Band {
+    pub fn contains(&self, distance: f32) -> bool {
+        self.inner <= distance && distance <= self.outer
+    }
+}
+
+/// Every passive that a jewel with `band` socketed at `center` reaches. Masteries, sockets and
+/// ascendancy nodes are never affected.
+pub fn nodes_in_band(graph: &TreeGraph, center: Pos2, band: Band) -> Vec<NodeIndex> {
+    graph
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| {
+            node.is_allocatable() && !node.is_jewel_socket && node.ascendancy_name.is_none()
+        })
+        .filter(|&(index, _)| band.contains(graph.positions[index].distance(center)))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Rewrites the stats of a passive in a jewel's radius before they're added up.
+pub trait StatTransform {
+    fn apply(&self, node: &Node, stats: &mut Vec<String>);
+}
+
+/// Conversion jewels such as Cold Steel, which make increases to one damage type in their
+/// radius apply to another.
+struct DamageConversion {
+    from: Vec<&'static str>,
+    to: String,
+}
+
+const DAMAGE_TYPES: [&str; 5] = ["Physical", "Fire", "Cold", "Lightning", "Chaos"];
+
+impl StatTransform for DamageConversion {
+    fn apply(&self, _node: &Node, stats: &mut Vec<String>) {
+        for stat in stats.iter_mut() {
+            if !stat.contains("increased") && !stat.contains("reduced") {
+                continue;
+            }
+            for from in &self.from {
+                *stat = stat.replace(&format!("{from} Damage"), &format!("{} Damage", self.to));
+            }
+        }
+    }
+}
+
+struct Patterns {
+    ring: Regex,
+    conversion: Regex,
+}
+
+// Compiled once on first use, like the modifier patterns.
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let regex = |pattern: &str| Regex::new(pattern).expect("jewel patterns are valid");
+        Patterns {
+            ring: regex(r"^Only affects Passives in (.+) Ring$"),
+            conversion: regex(
+                r"^Increases and Reductions to (\w+|other) Damage(?: Types)? in Radius are Transformed to apply to (\w+) Damage$",
+            ),
+        }
+    })
+}
+
+/// A jewel placed in a socket, described the way it reads in game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jewel {
+    pub name: String,
+    pub base: JewelBase,
+    #[serde(default)]
+    pub unique: bool,
+    /// Modifier lines, one stat per line.
+    #[serde(default)]
+    pub mods: Vec<String>,
+    /// Set for jewels that affect passives around their socket.
+    #[serde(default)]
+    pub radius: Option<JewelRadius>,
+}
+
+impl Jewel {
+    pub fn new(base: JewelBase) -> Self {
+        Self {
+            name: base.base_name().to_string(),
+            base,
+            unique: false,
+            mods: vec![],
+            radius: None,
+        }
+    }
+
+    /// The distances this jewel reaches, narrowed to a ring when a mod says so.
+    pub fn band(&self) -> Option<Band> {
+        let ring = self.mods.iter().find_map(|line| {
+            let captures = patterns().ring.captures(line)?;
+            JewelRadius::from_name(&captures[1])
+        });
+        match ring {
+            Some(ring) => Some(ring.ring()),
+            None => self.radius.map(|radius| Band {
+                inner: 0.0,
+                outer: radius.outer(),
+            }),
+        }
+    }
+
+    /// The passives this jewel reaches when socketed in `socket`.
+    pub fn affected_nodes(&self, graph: &TreeGraph, socket: usize) -> Vec<NodeIndex> {
+        match (self.band(), graph.index_of(socket)) {
+            (Some(band), Some(index)) => nodes_in_band(graph, graph.positions[index], band),
+            _ => vec![],
+        }
+    }
+
+    /// What the jewel does to the stats of the passives it reaches.
+    pub fn transforms(&self) -> Vec<Box<dyn StatTransform>> {
+        let mut transforms: Vec<Box<dyn StatTransform>> = vec![];
+        for line in &self.mods {
+            if let Some(captures) = patterns().conversion.captures(line) {
+                let to = captures[2].to_string();
+                let from = match &captures[1] {
+                    "other" => DAMAGE_TYPES.into_iter().filter(|&t| t != to).collect(),
+                    from => DAMAGE_TYPES.into_iter().filter(|&t| t == from).collect(),
+                };
+                transforms.push(Box::new(DamageConversion { from, to }));
+            }
+        }
+        transforms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::tree::Group;
+
+    /// A socket (1) at the origin with passives straight below it, each in its own group.
+    fn graph(nodes: Vec<(Node, f32)>) -> TreeGraph {
+        let mut by_id = HashMap::new();
+        let mut groups = HashMap::new();
+        let socket = Node {
+            is_jewel_socket: true,
+            ..Default::default()
+        };
+        for (id, (mut node, distance)) in [(socket, 0.0)].into_iter().chain(nodes).enumerate() {
+            let id = id + 1;
+            node.skill = Some(id);
+            node.group = Some(id);
+            by_id.insert(id.to_string(), node);
+            let group = Group {
+                y: distance,
+                orbits: vec![0],
+                ..Default::default()
+            };
+            groups.insert(id.to_string(), group);
+        }
+        TreeGraph::new(by_id, groups)
+    }
+
+    fn passive(stats: &[&str]) -> Node {
+        Node {
+            stats: stats.iter().map(|line| line.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn skills(graph: &TreeGraph, nodes: &[NodeIndex]) -> Vec<usize> {
+        let mut skills: Vec<usize> = nodes.iter().map(|&index| graph.skill(index)).collect();
+        skills.sort_unstable();
+        skills
+    }
+
+    #[test]
+    fn ring_mod_replaces_the_radius() {
+        let mut jewel = Jewel::new(JewelBase::Crimson);
+        assert_eq!(jewel.band(), None);
+
+        jewel.radius = Some(JewelRadius::Large);
+        assert_eq!(
+            jewel.band(),
+            Some(Band {
+                inner: 0.0,
+                outer: 1800.0
+            })
+        );
+
+        jewel.mods = vec![
+            "Passive Skills in Radius can be Allocated without being connected to your tree"
+                .to_string(),
+            "Only affects Passives in Medium Ring".to_string(),
+        ];
+        assert_eq!(jewel.band(), Some(JewelRadius::Medium.ring()));
+
+        jewel.radius = None;
+        jewel.mods = vec!["Only affects Passives in Very Large Ring".to_string()];
+        assert_eq!(jewel.band(), Some(JewelRadius::VeryLarge.ring()));
+
+        jewel.mods = vec!["Only affects Passives in Huge Ring".to_string()];
+        assert_eq!(jewel.band(), None);
+    }
+
+    #[test]
+    fn band_skips_masteries_sockets_and_ascendancies() {
+        let graph = graph(vec![
+            (passive(&[]), 500.0),
+            (passive(&[]), 1000.0),
+            (passive(&[]), 1320.0),
+            (passive(&[]), 1500.0),
+            (
+                Node {
+                    is_mastery: true,
+                    ..Default::default()
+                },
+                1000.0,
+            ),
+            (
+                Node {
+                    is_jewel_socket: true,
+                    ..Default::default()
+                },
+                1000.0,
+            ),
+            (
+                Node {
+                    ascendancy_name: Some("Juggernaut".to_string()),
+                    ..Default::default()
+                },
+                1000.0,
+            ),
+        ]);
+        let center = graph.positions[graph.index_of(1).unwrap()];
+        let ring = nodes_in_band(&graph, center, JewelRadius::Small.ring());
+        assert_eq!(skills(&graph, &ring), [3, 4]);
+
+        let mut jewel = Jewel::new(JewelBase::Cobalt);
+        jewel.radius = Some(JewelRadius::Large);
+        assert_eq!(
+            skills(&graph, &jewel.affected_nodes(&graph, 1)),
+            [2, 3, 4, 5]
+        );
+        assert!(jewel.affected_nodes(&graph, 99).is_empty());
+    }
+
+    #[test]
+    fn converts_increases_to_other_damage_types() {
+        let mut jewel = Jewel::new(JewelBase::Viridian);
+        jewel.mods = vec![
+            "Increases and Reductions to other Damage Types in Radius are Transformed to apply \
+             to Fire Damage"
+                .to_string(),
+        ];
+        let transforms = jewel.transforms();
+        assert_eq!(transforms.len(), 1);
+
+        let node = passive(&[]);
+        let mut stats: Vec<String> = [
+            "10% increased Cold Damage",
+            "8% reduced Physical Damage taken",
+            "12% increased Fire Damage",
+            "Adds 2 to 4 Lightning Damage",
+            "+10% to Chaos Damage over Time Multiplier",
+        ]
+        .map(String::from)
+        .into();
+        transforms[0].apply(&node, &mut stats);
+        assert_eq!(
+            stats,
+            [
+                "10% increased Fire Damage",
+                "8% reduced Fire Damage taken",
+                "12% increased Fire Damage",
+                "Adds 2 to 4 Lightning Damage",
+                "+10% to Chaos Damage over Time Multiplier",
+            ]
+        );
+
+        jewel.mods = vec![
+            "Increases and Reductions to Cold Damage in Radius are Transformed to apply to \
+             Physical Damage"
+                .to_string(),
+        ];
+        let mut stats = vec![
+            "10% increased Cold Damage".to_string(),
+            "10% increased Fire Damage".to_string(),
+        ];
+        jewel.transforms()[0].apply(&node, &mut stats);
+        assert_eq!(
+            stats,
+            ["10% increased Physical Damage", "10% increased Fire Damage"]
+        );
+        assert!(Jewel::new(JewelBase::Cobalt).transforms().is_empty());
+    }
+}
@@ -1,6 +1,20 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+pub mod build;
 mod camera;
-mod tree;
+pub mod cluster;
+pub mod graph;
+pub mod jewel;
+pub mod migration;
+pub mod modifiers;
+pub mod pathing;
+mod pob;
+pub mod search;
+pub mod spatial;
+pub mod stats;
+pub mod timeless;
+pub mod tree;
+mod tree_url;
 pub use app::TemplateApp;
+pub use tree::{TreeError, TreeExport};
@@ -18,10 +18,22 @@ fn main() -> eframe::Result<()> {
         ..Default::default()
     };
 
+    // Paths to `skilltree-export` data.json files replace the default tree, the first one being
+    // what new builds start on.
+    let paths: Vec<_> = std::env::args_os().skip(1).collect();
+    let trees = if paths.is_empty() {
+        poe_planner_egui::TreeExport::load_default().map(|tree| vec![tree])
+    } else {
+        paths
+            .into_iter()
+            .map(poe_planner_egui::TreeExport::from_file)
+            .collect()
+    };
+
     eframe::run_native(
         "PoE Planner",
         native_options,
-        Box::new(|cc| Ok(Box::new(poe_planner_egui::TemplateApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(poe_planner_egui::TemplateApp::new(cc, trees?)))),
     )
 }
 
@@ -34,16 +46,28 @@ fn main() {
     let web_options = eframe::WebOptions::default();
 
     wasm_bindgen_futures::spawn_local(async {
+        let loading_text = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("loading_text"));
+        let tree = match poe_planner_egui::TreeExport::load_default().await {
+            Ok(tree) => tree,
+            Err(e) => {
+                if let Some(loading_text) = &loading_text {
+                    loading_text.set_inner_html(
+                        "<p> The passive tree failed to load. See the developer console for details. </p>",
+                    );
+                }
+                panic!("failed to load tree data: {e}");
+            }
+        };
+
         let start_result = eframe::WebRunner::new()
             .start(
                 "the_canvas_id", // hardcode it
                 web_options,
-                Box::new(|cc| Box::new(poe_planner_egui::TemplateApp::new(cc))),
+                Box::new(|cc| Ok(Box::new(poe_planner_egui::TemplateApp::new(cc, vec![tree])))),
             )
             .await;
-        let loading_text = web_sys::window()
-            .and_then(|w| w.document())
-            .and_then(|d| d.get_element_by_id("loading_text"));
         match start_result {
             Ok(_) => {
                 loading_text.map(|e| e.remove());
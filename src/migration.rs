@@ -0,0 +1,253 @@
+use crate::{build::Build, graph::TreeGraph, pathing, tree::TreeExport};
+
+/// What happened to a build's nodes when it was moved onto another tree.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub from_version: String,
+    pub to_version: String,
+    /// Allocated nodes with no counterpart in the new tree, already refunded.
+    pub removed: Vec<(usize, String)>,
+    /// `(skill, old name, new name)` for nodes that kept their id but not their name.
+    pub renamed: Vec<(usize, String, String)>,
+    /// `(old skill, new skill)` for nodes whose id disappeared but whose name is unique in both
+    /// trees, so the allocation was carried over.
+    pub remapped: Vec<(usize, usize)>,
+    /// Nodes still allocated that the new tree no longer connects to the class or ascendancy
+    /// start.
+    pub disconnected: Vec<usize>,
+}
+
+impl MigrationReport {
+    pub fn is_clean(&self) -> bool {
+        self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.remapped.is_empty()
+            && self.disconnected.is_empty()
+    }
+}
+
+/// Moves `build` from `from` onto `to`, matching nodes by skill id and then by name. Nodes
+/// that can't be matched are refunded; disconnected ones are kept and reported so they can be
+/// reconnected or refunded.
+pub fn migrate(
+    build: &Build,
+    class_index: usize,
+    ascendancy: Option<&str>,
+    from: &TreeExport,
+    to: &TreeExport,
+) -> (Build, MigrationReport) {
+    let old = &from.graph;
+    let new = &to.graph;
+    let mut report = MigrationReport {
+        from_version: from.tree.clone(),
+        to_version: to.tree.clone(),
+        ..Default::default()
+    };
+    let mut migrated = Build {
+        tree_version: to.tree.clone(),
+        ascendancy: build.ascendancy,
+        progress: build.progress.clone(),
+        ..Default::default()
+    };
+
+    let mut allocated: Vec<usize> = build.allocated.iter().copied().collect();
+    allocated.sort_unstable();
+    for skill in allocated {
+        let old_name = name_of(old, skill);
+        let new_skill = match new.node(skill) {
+            Some(node) => {
+                let new_name = node.name.clone().unwrap_or_default();
+                if new_name != old_name {
+                    report.renamed.push((skill, old_name, new_name));
+                }
+                skill
+            }
+            None => match unique_by_name(old, &old_name).and(unique_by_name(new, &old_name)) {
+                Some(new_skill) => {
+                    report.remapped.push((skill, new_skill));
+                    new_skill
+                }
+                None => {
+                    report.removed.push((skill, old_name));
+                    continue;
+                }
+            },
+        };
+        migrated.allocated.insert(new_skill);
+        if let Some(&effect) = build.mastery_effects.get(&skill) {
+            migrated.mastery_effects.insert(new_skill, effect);
+        }
+        if let Some(jewel) = build.jewels.get(&skill) {
+            migrated.jewels.insert(new_skill, jewel.clone());
+        }
+    }
+    // Jewels left in refunded sockets follow the socket id, if it's still a socket.
+    for (&socket, jewel) in &build.jewels {
+        if !build.is_allocated(socket) && new.node(socket).is_some_and(|node| node.is_jewel_socket)
+        {
+            migrated.jewels.insert(socket, jewel.clone());
+        }
+    }
+
+    let starts = new.start_nodes(class_index, ascendancy);
+    let connected = pathing::connected_to_start(new, &migrated.allocated, &starts);
+    report.disconnected = migrated.allocated.difference(&connected).copied().collect();
+    report.disconnected.sort_unstable();
+
+    (migrated, report)
+}
+
+/// Allocates the cheapest paths that tie disconnected nodes back to `starts`, nearest first,
+/// and returns the nodes it added. Anything it can't reach stays disconnected.
+pub fn reconnect(graph: &TreeGraph, build: &mut Build, starts: &[usize]) -> Vec<usize> {
+    let mut added = vec![];
+    loop {
+        let connected = pathing::connected_to_start(graph, &build.allocated, starts);
+        let anchored = Build {
+            allocated: connected.clone(),
+            ..Default::default()
+        };
+        let cheapest = build
+            .allocated
+            .difference(&connected)
+            .filter_map(|&skill| pathing::shortest_path(graph, &anchored, starts, skill))
+            .min_by_key(|path| path.len());
+        let Some(path) = cheapest else {
+            return added;
+        };
+
+        // The target is part of the path, so every round connects at least one more node.
+        added.extend(path.iter().filter(|&&skill| !build.is_allocated(skill)));
+        build.allocate_path(&path);
+    }
+}
+
+fn name_of(graph: &TreeGraph, skill: usize) -> String {
+    graph
+        .node(skill)
+        .and_then(|node| node.name.clone())
+        .unwrap_or_default()
+}
+
+/// The only allocatable node called `name`, if there is exactly one.
+fn unique_by_name(graph: &TreeGraph, name: &str) -> Option<usize> {
+    let mut matches = graph
+        .nodes
+        .iter()
+        .filter(|node| node.is_allocatable() && node.name.as_deref() == Some(name));
+    let found = matches.next()?.skill;
+    match matches.next() {
+        Some(_) => None,
+        None => found,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::tree::Node;
+
+    fn node(skill: usize, name: &str, out: &[usize]) -> (String, Node) {
+        let node = Node {
+            skill: Some(skill),
+            name: Some(name.to_string()),
+            out: out.iter().map(|id| id.to_string()).collect(),
+            class_start_index: (skill == 100).then_some(0),
+            ..Default::default()
+        };
+        (skill.to_string(), node)
+    }
+
+    fn tree(version: &str, nodes: Vec<(String, Node)>) -> TreeExport {
+        TreeExport {
+            tree: version.to_string(),
+            graph: TreeGraph::new(nodes.into_iter().collect(), HashMap::new()),
+            ..Default::default()
+        }
+    }
+
+    /// 100 - 1 - 2 - 3 - 4 - 6, with 5 hanging off 2.
+    fn old_tree() -> TreeExport {
+        tree(
+            "old",
+            vec![
+                node(100, "Start", &[1]),
+                node(1, "Alpha", &[2]),
+                node(2, "Beta", &[3, 5]),
+                node(3, "Gamma", &[4]),
+                node(4, "Delta", &[6]),
+                node(5, "Epsilon", &[]),
+                node(6, "Zeta", &[]),
+            ],
+        )
+    }
+
+    /// Beta is renamed, Gamma gets a new id, Epsilon is gone and Zeta moved next to a new node
+    /// off Alpha, with a longer way round through 8 and 9.
+    fn new_tree() -> TreeExport {
+        tree(
+            "new",
+            vec![
+                node(100, "Start", &[1]),
+                node(1, "Alpha", &[2, 7]),
+                node(2, "Beta Prime", &[30]),
+                node(30, "Gamma", &[4]),
+                node(4, "Delta", &[8]),
+                node(8, "Eta", &[9]),
+                node(9, "Theta", &[6]),
+                node(7, "Iota", &[6]),
+                node(6, "Zeta", &[]),
+            ],
+        )
+    }
+
+    fn migrated() -> (Build, MigrationReport) {
+        let build = Build {
+            allocated: [1, 2, 3, 4, 5, 6].into(),
+            ..Default::default()
+        };
+        migrate(&build, 0, None, &old_tree(), &new_tree())
+    }
+
+    #[test]
+    fn reports_what_changed() {
+        let (build, report) = migrated();
+        assert_eq!(
+            (report.from_version.as_str(), report.to_version.as_str()),
+            ("old", "new")
+        );
+        assert_eq!(report.removed, [(5, "Epsilon".to_string())]);
+        assert_eq!(
+            report.renamed,
+            [(2, "Beta".to_string(), "Beta Prime".to_string())]
+        );
+        assert_eq!(report.remapped, [(3, 30)]);
+        assert_eq!(report.disconnected, [6]);
+        assert_eq!(build.tree_version, "new");
+        assert_eq!(build.allocated, [1, 2, 30, 4, 6].into());
+    }
+
+    #[test]
+    fn clean_when_nothing_changed() {
+        let build = Build {
+            allocated: [1, 2, 3].into(),
+            ..Default::default()
+        };
+        let (migrated, report) = migrate(&build, 0, None, &old_tree(), &old_tree());
+        assert!(report.is_clean());
+        assert_eq!(migrated.allocated, build.allocated);
+    }
+
+    #[test]
+    fn reconnects_at_minimum_cost() {
+        let (mut build, _) = migrated();
+        let graph = &new_tree().graph;
+        let starts = graph.start_nodes(0, None);
+        let added = reconnect(graph, &mut build, &starts);
+        assert_eq!(added, [7]);
+        let connected = pathing::connected_to_start(graph, &build.allocated, &starts);
+        assert_eq!(connected, build.allocated);
+    }
+}
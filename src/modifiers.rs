@@ -0,0 +1,439 @@
+use std::{fmt, sync::OnceLock};
+
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModifierKind {
+    /// Summed with other increases and reductions, then applied once.
+    Increased,
+    /// Applied multiplicatively, each on its own.
+    More,
+    /// Added straight onto the stat, e.g. `+20 to Strength`.
+    Flat,
+    /// A base value such as added damage or a chance, only meaningful on its own.
+    Base,
+    /// An on/off effect without a value, mostly from keystones.
+    Flag,
+}
+
+impl fmt::Display for ModifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Increased => "increased",
+            Self::More => "more",
+            Self::Flat => "flat",
+            Self::Base => "base",
+            Self::Flag => "flag",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modifier {
+    /// Snake-cased stat name, e.g. `physical_damage` or `maximum_life`.
+    pub stat: String,
+    pub kind: ModifierKind,
+    /// Negative for reductions and "less" modifiers, 1.0 for flags.
+    pub value: f32,
+    /// Trailing qualifiers such as `while holding a shield`, as written.
+    pub conditions: Vec<String>,
+    /// The stat line this was parsed from.
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedStats {
+    pub modifiers: Vec<Modifier>,
+    /// Lines with numbers in them that didn't match any known pattern.
+    pub unparsed: Vec<String>,
+}
+
+impl ParsedStats {
+    pub fn extend(&mut self, other: ParsedStats) {
+        self.modifiers.extend(other.modifiers);
+        self.unparsed.extend(other.unparsed);
+    }
+}
+
+struct Patterns {
+    increased: Regex,
+    more: Regex,
+    added: Regex,
+    chance: Regex,
+    regenerate: Regex,
+    flat: Regex,
+    condition: Regex,
+}
+
+// Compiled once on first use, the same way `tree::ascendancy_starts` builds its table.
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let regex = |pattern: &str| Regex::new(pattern).expect("modifier patterns are valid");
+        Patterns {
+            increased: regex(r"^(\d+(?:\.\d+)?)% (increased|reduced) (.+)$"),
+            more: regex(r"^(\d+(?:\.\d+)?)% (more|less) (.+)$"),
+            added: regex(r"^Adds (\d+) to (\d+) (.+?) Damage(?: (to .+))?$"),
+            chance: regex(r"^\+?(\d+(?:\.\d+)?)% (chance to .+)$"),
+            regenerate: regex(r"^Regenerate (\d+(?:\.\d+)?)(%)? (?:of )?(.+?) per second(.*)$"),
+            flat: regex(r"^([+-]\d+(?:\.\d+)?)(%)? (?:to )?(.+)$"),
+            condition: regex(r" (while|if|when|during|against|per|with|for) "),
+        }
+    })
+}
+
+/// Parses every line of a node's stats. Lines may hold several stats separated by newlines.
+pub fn parse_stats<'a>(lines: impl IntoIterator<Item = &'a String>) -> ParsedStats {
+    let mut parsed = ParsedStats::default();
+    for line in lines.into_iter().flat_map(|line| line.lines()) {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_line(line) {
+            Some(modifiers) => parsed.modifiers.extend(modifiers),
+            None => parsed.unparsed.push(line.to_string()),
+        }
+    }
+    parsed
+}
+
+/// Turns one stat line into modifiers, or `None` if it isn't understood.
+pub fn parse_line(line: &str) -> Option<Vec<Modifier>> {
+    let mut modifiers = match_line(line)?;
+    for modifier in &mut modifiers {
+        modifier.text = line.to_string();
+    }
+    Some(modifiers)
+}
+
+fn match_line(line: &str) -> Option<Vec<Modifier>> {
+    let patterns = patterns();
+    let number = |text: &str| text.parse::<f32>().ok();
+
+    if let Some(captures) = patterns.increased.captures(line) {
+        let sign = if &captures[2] == "reduced" { -1.0 } else { 1.0 };
+        return Some(modifiers(
+            &captures[3],
+            ModifierKind::Increased,
+            sign * number(&captures[1])?,
+        ));
+    }
+    if let Some(captures) = patterns.more.captures(line) {
+        let sign = if &captures[2] == "less" { -1.0 } else { 1.0 };
+        return Some(modifiers(
+            &captures[3],
+            ModifierKind::More,
+            sign * number(&captures[1])?,
+        ));
+    }
+    if let Some(captures) = patterns.added.captures(line) {
+        let damage = stat_id(&format!("added {} damage", &captures[3]));
+        let conditions: Vec<String> = captures
+            .get(4)
+            .map(|m| m.as_str().to_string())
+            .into_iter()
+            .collect();
+        return Some(vec![
+            Modifier {
+                stat: format!("{damage}_min"),
+                kind: ModifierKind::Base,
+                value: number(&captures[1])?,
+                conditions: conditions.clone(),
+                text: String::new(),
+            },
+            Modifier {
+                stat: format!("{damage}_max"),
+                kind: ModifierKind::Base,
+                value: number(&captures[2])?,
+                conditions,
+                text: String::new(),
+            },
+        ]);
+    }
+    if let Some(captures) = patterns.chance.captures(line) {
+        return Some(modifiers(
+            &captures[2],
+            ModifierKind::Base,
+            number(&captures[1])?,
+        ));
+    }
+    if let Some(captures) = patterns.regenerate.captures(line) {
+        let unit = if captures.get(2).is_some() {
+            " percent"
+        } else {
+            ""
+        };
+        let stat = format!("{} regeneration{unit}{}", &captures[3], &captures[4]);
+        return Some(modifiers(&stat, ModifierKind::Base, number(&captures[1])?));
+    }
+    if let Some(captures) = patterns.flat.captures(line) {
+        let unit = if captures.get(2).is_some() {
+            " percent"
+        } else {
+            ""
+        };
+        let value = number(&captures[1])?;
+        let (stat, conditions) = split_conditions(&captures[3]);
+        return Some(
+            attributes(stat)
+                .into_iter()
+                .map(|stat| Modifier {
+                    stat: stat_id(&format!("{stat}{unit}")),
+                    kind: ModifierKind::Flat,
+                    value,
+                    conditions: conditions.clone(),
+                    text: String::new(),
+                })
+                .collect(),
+        );
+    }
+
+    // Anything without a number is an on/off effect; anything with one we can't read.
+    if line.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(vec![Modifier {
+        stat: stat_id(line),
+        kind: ModifierKind::Flag,
+        value: 1.0,
+        conditions: vec![],
+        text: String::new(),
+    }])
+}
+
+fn modifiers(text: &str, kind: ModifierKind, value: f32) -> Vec<Modifier> {
+    let (stat, conditions) = split_conditions(text);
+    vec![Modifier {
+        stat: stat_id(stat),
+        kind,
+        value,
+        conditions,
+        text: String::new(),
+    }]
+}
+
+/// Splits `Attack Speed while holding a Shield` into the stat and its qualifiers.
+fn split_conditions(text: &str) -> (&str, Vec<String>) {
+    let starts: Vec<usize> = patterns()
+        .condition
+        .find_iter(text)
+        .map(|m| m.start())
+        .collect();
+    let Some(&first) = starts.first() else {
+        return (text, vec![]);
+    };
+    let ends = starts.iter().skip(1).copied().chain([text.len()]);
+    let conditions = starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| text[start..end].trim().to_string())
+        .collect();
+    (&text[..first], conditions)
+}
+
+/// The attribute stats a combined line like `+10 to Strength and Dexterity` stands for.
+fn attributes(stat: &str) -> Vec<&str> {
+    match stat {
+        "all Attributes" => vec!["Strength", "Dexterity", "Intelligence"],
+        "Strength and Dexterity" => vec!["Strength", "Dexterity"],
+        "Strength and Intelligence" => vec!["Strength", "Intelligence"],
+        "Dexterity and Intelligence" => vec!["Dexterity", "Intelligence"],
+        _ => vec![stat],
+    }
+}
+
+fn stat_id(text: &str) -> String {
+    let mut id = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    id.trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(stat, kind, value, conditions)` for every modifier on `line`.
+    fn parse(line: &str) -> Vec<(String, ModifierKind, f32, Vec<String>)> {
+        parse_line(line)
+            .unwrap_or_else(|| panic!("{line:?} didn't parse"))
+            .into_iter()
+            .map(|m| {
+                assert_eq!(m.text, line);
+                (m.stat, m.kind, m.value, m.conditions)
+            })
+            .collect()
+    }
+
+    fn one(
+        stat: &str,
+        kind: ModifierKind,
+        value: f32,
+        conditions: &[&str],
+    ) -> Vec<(String, ModifierKind, f32, Vec<String>)> {
+        let conditions = conditions.iter().map(|c| c.to_string()).collect();
+        vec![(stat.to_string(), kind, value, conditions)]
+    }
+
+    #[test]
+    fn increased_and_reduced() {
+        use ModifierKind::Increased;
+        assert_eq!(
+            parse("10% increased maximum Life"),
+            one("maximum_life", Increased, 10.0, &[])
+        );
+        assert_eq!(
+            parse("8% reduced Mana Cost of Skills"),
+            one("mana_cost_of_skills", Increased, -8.0, &[])
+        );
+        assert_eq!(
+            parse("0.4% increased Movement Speed"),
+            one("movement_speed", Increased, 0.4, &[])
+        );
+    }
+
+    #[test]
+    fn more_and_less() {
+        use ModifierKind::More;
+        assert_eq!(
+            parse("40% more Damage with Hits and Ailments"),
+            one("damage", More, 40.0, &["with Hits and Ailments"])
+        );
+        assert_eq!(
+            parse("20% less Attack Speed"),
+            one("attack_speed", More, -20.0, &[])
+        );
+    }
+
+    #[test]
+    fn added_damage() {
+        assert_eq!(
+            parse("Adds 3 to 5 Physical Damage to Attacks"),
+            vec![
+                (
+                    "added_physical_damage_min".to_string(),
+                    ModifierKind::Base,
+                    3.0,
+                    vec!["to Attacks".to_string()]
+                ),
+                (
+                    "added_physical_damage_max".to_string(),
+                    ModifierKind::Base,
+                    5.0,
+                    vec!["to Attacks".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(parse("Adds 1 to 12 Lightning Damage").len(), 2);
+    }
+
+    #[test]
+    fn flat_and_percent() {
+        use ModifierKind::{Base, Flat};
+        assert_eq!(parse("+30 to Strength"), one("strength", Flat, 30.0, &[]));
+        assert_eq!(
+            parse("+12% to Fire Resistance"),
+            one("fire_resistance_percent", Flat, 12.0, &[])
+        );
+        assert_eq!(
+            parse("-5% to all Elemental Resistances"),
+            one("all_elemental_resistances_percent", Flat, -5.0, &[])
+        );
+        assert_eq!(
+            parse("10% chance to Freeze"),
+            one("chance_to_freeze", Base, 10.0, &[])
+        );
+        assert_eq!(
+            parse("Regenerate 0.5% of Life per second"),
+            one("life_regeneration_percent", Base, 0.5, &[])
+        );
+        assert_eq!(
+            parse("Regenerate 3 Mana per second"),
+            one("mana_regeneration", Base, 3.0, &[])
+        );
+    }
+
+    #[test]
+    fn splits_combined_attributes() {
+        let stats = |line: &str| -> Vec<String> {
+            parse(line).into_iter().map(|(stat, ..)| stat).collect()
+        };
+        assert_eq!(
+            stats("+10 to all Attributes"),
+            ["strength", "dexterity", "intelligence"]
+        );
+        assert_eq!(
+            stats("+20 to Strength and Intelligence"),
+            ["strength", "intelligence"]
+        );
+        assert_eq!(
+            stats("+16 to Dexterity and Intelligence"),
+            ["dexterity", "intelligence"]
+        );
+    }
+
+    #[test]
+    fn splits_conditions() {
+        use ModifierKind::{Flat, Increased};
+        assert_eq!(
+            parse("15% increased Attack Speed while holding a Shield"),
+            one("attack_speed", Increased, 15.0, &["while holding a Shield"])
+        );
+        assert_eq!(
+            parse("4% increased Damage per Frenzy Charge if you've Hit Recently"),
+            one(
+                "damage",
+                Increased,
+                4.0,
+                &["per Frenzy Charge", "if you've Hit Recently"]
+            )
+        );
+        assert_eq!(
+            parse("+1% to Critical Strike Multiplier per 10 Dexterity"),
+            one(
+                "critical_strike_multiplier_percent",
+                Flat,
+                1.0,
+                &["per 10 Dexterity"]
+            )
+        );
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(
+            parse("Never deal Critical Strikes"),
+            one("never_deal_critical_strikes", ModifierKind::Flag, 1.0, &[])
+        );
+        assert_eq!(
+            parse("Your hits can't be Evaded"),
+            one("your_hits_can_t_be_evaded", ModifierKind::Flag, 1.0, &[])
+        );
+    }
+
+    #[test]
+    fn collects_unparsed_lines() {
+        let lines = [
+            "10% increased maximum Life\n+30 to Strength".to_string(),
+            "Gain 1 Endurance Charge every 4 seconds".to_string(),
+            "  ".to_string(),
+            "Never deal Critical Strikes".to_string(),
+        ];
+        let parsed = parse_stats(&lines);
+        assert_eq!(parsed.modifiers.len(), 3);
+        assert_eq!(parsed.modifiers[1].text, "+30 to Strength");
+        assert_eq!(parsed.unparsed, ["Gain 1 Endurance Charge every 4 seconds"]);
+
+        let mut total = ParsedStats::default();
+        total.extend(parsed);
+        total.extend(parse_stats(&["Lose 2 Mana per 3 Seconds".to_string()]));
+        assert_eq!(total.unparsed.len(), 2);
+    }
+}
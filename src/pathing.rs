@@ -0,0 +1,122 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    build::Build,
+    graph::{NodeIndex, TreeGraph},
+};
+
+/// Finds the cheapest chain of unallocated nodes connecting `target` to one of `starts` or
+/// anything already allocated. The returned nodes are ordered outwards, ending with `target`.
+pub fn shortest_path(
+    graph: &TreeGraph,
+    build: &Build,
+    starts: &[usize],
+    target: usize,
+) -> Option<Vec<usize>> {
+    if build.is_allocated(target) || starts.contains(&target) {
+        return None;
+    }
+    let target = graph.index_of(target)?;
+
+    let mut previous: Vec<Option<NodeIndex>> = vec![None; graph.nodes.len()];
+    let mut visited = vec![false; graph.nodes.len()];
+    let mut queue = VecDeque::new();
+    for source in build
+        .allocated
+        .iter()
+        .chain(starts)
+        .filter_map(|&skill| graph.index_of(skill))
+    {
+        visited[source] = true;
+        queue.push_back(source);
+    }
+
+    while let Some(current) = queue.pop_front() {
+        if current == target {
+            let mut path = vec![graph.skill(target)];
+            let mut step = target;
+            // Sources are the only visited nodes without a predecessor.
+            while let Some(prev) = previous[step] {
+                if previous[prev].is_none() {
+                    break;
+                }
+                path.push(graph.skill(prev));
+                step = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for &next in &graph.adjacency[current] {
+            if visited[next] || !is_traversable(graph, next) {
+                continue;
+            }
+            visited[next] = true;
+            previous[next] = Some(current);
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+/// Walks the allocated nodes outwards from `starts` and returns every one it reaches.
+/// Masteries count as reached as soon as anything else in their group is.
+pub fn connected_to_start(
+    graph: &TreeGraph,
+    allocated: &HashSet<usize>,
+    starts: &[usize],
+) -> HashSet<usize> {
+    let mut reached = HashSet::new();
+    let mut queue: VecDeque<NodeIndex> = starts
+        .iter()
+        .filter_map(|&skill| graph.index_of(skill))
+        .collect();
+
+    while let Some(current) = queue.pop_front() {
+        for &next in &graph.adjacency[current] {
+            let skill = graph.skill(next);
+            if allocated.contains(&skill) && reached.insert(skill) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let masteries: Vec<usize> = allocated
+        .iter()
+        .copied()
+        .filter(|&skill| {
+            graph.index_of(skill).is_some_and(|index| {
+                graph.nodes[index].is_mastery
+                    && graph.group_of(index).is_some_and(|group| {
+                        graph.group_nodes[group]
+                            .iter()
+                            .any(|&other| reached.contains(&graph.skill(other)))
+                    })
+            })
+        })
+        .collect();
+    reached.extend(masteries);
+
+    reached
+}
+
+/// Every allocated node that would be refunded by removing `skill`, including `skill` itself.
+pub fn orphaned_by_removing(
+    graph: &TreeGraph,
+    build: &Build,
+    starts: &[usize],
+    skill: usize,
+) -> HashSet<usize> {
+    let mut remaining = build.allocated.clone();
+    if !remaining.remove(&skill) {
+        return HashSet::new();
+    }
+    let connected = connected_to_start(graph, &remaining, starts);
+    build.allocated.difference(&connected).copied().collect()
+}
+
+/// Other classes' start nodes act as walls, as they do in game.
+fn is_traversable(graph: &TreeGraph, index: NodeIndex) -> bool {
+    graph.nodes[index].is_allocatable()
+}
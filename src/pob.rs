@@ -0,0 +1,673 @@
+use std::{
+    fmt,
+    fmt::Write as _,
+    io::{Read, Write as _},
+};
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use roxmltree::{Document, Node};
+
+use crate::jewel::{Jewel, JewelBase, JewelRadius};
+
+// Path of Building swaps `+/` for `-_` but otherwise keeps standard padded base64.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// The parts of a Path of Building document the planner understands, plus everything else kept
+/// verbatim so it can be written back unchanged.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PobBuild {
+    pub class_id: u8,
+    pub ascendancy_id: u8,
+    pub tree_version: String,
+    /// Every allocated node, including class and ascendancy starts.
+    pub nodes: Vec<usize>,
+    /// `(mastery node, effect)` pairs.
+    pub mastery_effects: Vec<(usize, usize)>,
+    pub notes: String,
+    /// Attributes of `<Build>`, in document order.
+    pub build_attributes: Vec<(String, String)>,
+    /// Raw XML of the children of `<Build>`.
+    pub build_children: Vec<String>,
+    /// Attributes of `<Tree>` other than `activeSpec`.
+    pub tree_attributes: Vec<(String, String)>,
+    /// Attributes of the active `<Spec>` the planner doesn't use, such as its title.
+    pub spec_attributes: Vec<(String, String)>,
+    /// Raw XML of the children of the active `<Spec>` other than `<Sockets>`.
+    pub spec_children: Vec<String>,
+    /// Raw XML of the inactive `<Spec>` elements, in document order.
+    pub other_specs: Vec<String>,
+    /// How many of `other_specs` come before the active spec.
+    pub active_spec: usize,
+    /// `(node, item id)` for every jewel socketed in the active spec.
+    pub sockets: Vec<(usize, usize)>,
+    pub items: Vec<PobItem>,
+    /// Attributes of `<Items>`, such as the active item set.
+    pub items_attributes: Vec<(String, String)>,
+    /// Raw XML of the children of `<Items>` other than `<Item>`, such as item sets.
+    pub items_children: Vec<String>,
+    /// Raw XML of every top-level section other than `<Build>`, `<Tree>` and `<Notes>`.
+    pub sections: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PobItem {
+    pub id: usize,
+    /// The item in Path of Building's plain-text format, rarity line first.
+    pub text: String,
+    /// Raw XML of the item's child elements, such as mod ranges.
+    pub children: Vec<String>,
+}
+
+impl PobBuild {
+    /// Overwrites a `<Build>` attribute in place, or appends it if it's missing.
+    pub fn set_build_attribute(&mut self, name: &str, value: String) {
+        match self.build_attributes.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.build_attributes.push((name.to_string(), value)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PobError {
+    Base64(base64::DecodeError),
+    Inflate(std::io::Error),
+    Xml(roxmltree::Error),
+    MissingElement(&'static str),
+    InvalidAttribute(&'static str, String),
+}
+
+impl fmt::Display for PobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base64(e) => write!(f, "build code is not valid base64: {e}"),
+            Self::Inflate(e) => write!(f, "build code could not be decompressed: {e}"),
+            Self::Xml(e) => write!(f, "build code contains invalid XML: {e}"),
+            Self::MissingElement(name) => write!(f, "build is missing its <{name}> element"),
+            Self::InvalidAttribute(name, value) => {
+                write!(f, "build has an invalid {name} attribute: {value:?}")
+            }
+        }
+    }
+}
+
+impl From<base64::DecodeError> for PobError {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Base64(e)
+    }
+}
+
+impl From<roxmltree::Error> for PobError {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
+    }
+}
+
+pub fn decode(code: &str) -> Result<PobBuild, PobError> {
+    let compressed = ENGINE.decode(code.trim())?;
+    let mut xml = String::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .read_to_string(&mut xml)
+        .map_err(PobError::Inflate)?;
+    parse_xml(&xml)
+}
+
+pub fn encode(build: &PobBuild) -> String {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing into a `Vec` can't fail.
+    encoder
+        .write_all(to_xml(build).as_bytes())
+        .and_then(|_| encoder.finish())
+        .map(|compressed| ENGINE.encode(compressed))
+        .unwrap_or_default()
+}
+
+pub fn parse_xml(xml: &str) -> Result<PobBuild, PobError> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    if !root.has_tag_name("PathOfBuilding") {
+        return Err(PobError::MissingElement("PathOfBuilding"));
+    }
+    let raw = |node: Node<'_, '_>| xml[node.range()].to_string();
+
+    let mut build = PobBuild::default();
+    let mut found_tree = false;
+    for section in root.children().filter(Node::is_element) {
+        match section.tag_name().name() {
+            "Build" => {
+                build.build_attributes = section
+                    .attributes()
+                    .map(|a| (a.name().to_string(), a.value().to_string()))
+                    .collect();
+                build.build_children = section
+                    .children()
+                    .filter(Node::is_element)
+                    .map(raw)
+                    .collect();
+            }
+            "Tree" => {
+                found_tree = true;
+                let active: usize = attribute(section, "activeSpec")?.unwrap_or(1);
+                build.tree_attributes = section
+                    .attributes()
+                    .filter(|a| a.name() != "activeSpec")
+                    .map(|a| (a.name().to_string(), a.value().to_string()))
+                    .collect();
+                for (index, spec) in section
+                    .children()
+                    .filter(|n| n.has_tag_name("Spec"))
+                    .enumerate()
+                {
+                    if index + 1 == active {
+                        read_spec(spec, &mut build, raw)?;
+                    } else {
+                        build.other_specs.push(raw(spec));
+                    }
+                }
+                build.active_spec = active.saturating_sub(1).min(build.other_specs.len());
+            }
+            "Notes" => build.notes = section.text().unwrap_or_default().trim().to_string(),
+            "Items" => read_items(section, &mut build, raw)?,
+            _ => build.sections.push(raw(section)),
+        }
+    }
+
+    if !found_tree {
+        return Err(PobError::MissingElement("Tree"));
+    }
+    Ok(build)
+}
+
+const SPEC_ATTRIBUTES: [&str; 5] = [
+    "treeVersion",
+    "classId",
+    "ascendClassId",
+    "nodes",
+    "masteryEffects",
+];
+
+fn read_spec<'a, 'input>(
+    spec: Node<'a, 'input>,
+    build: &mut PobBuild,
+    raw: impl Fn(Node<'a, 'input>) -> String,
+) -> Result<(), PobError> {
+    build.class_id = attribute(spec, "classId")?.unwrap_or_default();
+    build.ascendancy_id = attribute(spec, "ascendClassId")?.unwrap_or_default();
+    build.tree_version = spec
+        .attribute("treeVersion")
+        .unwrap_or_default()
+        .to_string();
+
+    let nodes = spec.attribute("nodes").unwrap_or_default();
+    build.nodes = nodes
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.trim()
+                .parse()
+                .map_err(|_| PobError::InvalidAttribute("nodes", nodes.to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Formatted as `{node,effect},{node,effect}`.
+    let effects = spec.attribute("masteryEffects").unwrap_or_default();
+    build.mastery_effects = effects
+        .split('}')
+        .map(|pair| pair.trim_start_matches([',', '{']))
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (node, effect) = pair.split_once(',')?;
+            Some((node.trim().parse().ok()?, effect.trim().parse().ok()?))
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(|| PobError::InvalidAttribute("masteryEffects", effects.to_string()))?;
+
+    build.spec_attributes = spec
+        .attributes()
+        .filter(|a| !SPEC_ATTRIBUTES.contains(&a.name()))
+        .map(|a| (a.name().to_string(), a.value().to_string()))
+        .collect();
+    build.spec_children = spec
+        .children()
+        .filter(|n| n.is_element() && !n.has_tag_name("Sockets"))
+        .map(raw)
+        .collect();
+
+    for socket in spec
+        .children()
+        .filter(|n| n.has_tag_name("Sockets"))
+        .flat_map(|sockets| sockets.children())
+        .filter(|n| n.has_tag_name("Socket"))
+    {
+        let node = attribute(socket, "nodeId")?;
+        let item = attribute(socket, "itemId")?;
+        // PoB writes an item id of 0 for empty sockets.
+        if let (Some(node), Some(item)) = (node, item.filter(|&id: &usize| id != 0)) {
+            build.sockets.push((node, item));
+        }
+    }
+    Ok(())
+}
+
+fn read_items<'a, 'input>(
+    items: Node<'a, 'input>,
+    build: &mut PobBuild,
+    raw: impl Fn(Node<'a, 'input>) -> String,
+) -> Result<(), PobError> {
+    build.items_attributes = items
+        .attributes()
+        .map(|a| (a.name().to_string(), a.value().to_string()))
+        .collect();
+    for child in items.children().filter(Node::is_element) {
+        if !child.has_tag_name("Item") {
+            build.items_children.push(raw(child));
+            continue;
+        }
+        build.items.push(PobItem {
+            id: attribute(child, "id")?.unwrap_or_default(),
+            text: child
+                .children()
+                .filter(Node::is_text)
+                .filter_map(|n| n.text())
+                .collect::<String>()
+                .trim()
+                .to_string(),
+            children: child
+                .children()
+                .filter(Node::is_element)
+                .map(&raw)
+                .collect(),
+        });
+    }
+    Ok(())
+}
+
+/// Reads a jewel out of an item's text, or `None` if the item isn't a jewel the planner knows.
+pub fn parse_jewel_item(text: &str) -> Option<Jewel> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let rarity = lines.next()?.strip_prefix("Rarity: ")?.to_ascii_uppercase();
+    let named = matches!(rarity.as_str(), "RARE" | "UNIQUE" | "RELIC");
+    // Magic and normal items only have the one line, with the base somewhere in it.
+    let name = lines.next()?;
+    let base = match rarity.as_str() {
+        "MAGIC" | "NORMAL" => JewelBase::from_magic_name(name)?,
+        _ if named => JewelBase::from_base_name(lines.next()?)?,
+        _ => return None,
+    };
+
+    let mut radius = None;
+    let mut mods = vec![];
+    for line in lines {
+        if let Some(size) = line.strip_prefix("Radius: ") {
+            radius = JewelRadius::from_name(size);
+        } else if !is_item_property(line) {
+            let line = strip_mod_tags(line);
+            if !line.is_empty() {
+                mods.push(line.to_string());
+            }
+        }
+    }
+    Some(Jewel {
+        name: name.to_string(),
+        base,
+        unique: rarity != "RARE" && named,
+        mods,
+        radius,
+    })
+}
+
+/// Writes `jewel` in the item text format `parse_jewel_item` reads.
+pub fn jewel_item(jewel: &Jewel) -> String {
+    let rarity = if jewel.unique { "UNIQUE" } else { "RARE" };
+    let mut text = format!("Rarity: {rarity}\n{}\n{}", jewel.name, jewel.base);
+    if let Some(radius) = jewel.radius {
+        let _ = write!(text, "\nRadius: {radius}");
+    }
+    text.push_str("\nImplicits: 0");
+    for line in &jewel.mods {
+        let _ = write!(text, "\n{line}");
+    }
+    text
+}
+
+/// Lines like `Item Level: 84` or `Implicits: 0` that describe the item rather than a mod.
+fn is_item_property(line: &str) -> bool {
+    line.split_once(": ")
+        .is_some_and(|(key, _)| key.chars().all(|c| c.is_ascii_alphabetic() || c == ' '))
+}
+
+/// Drops PoB's `{crafted}`, `{range:0.5}` and similar prefixes from a mod line.
+fn strip_mod_tags(mut line: &str) -> &str {
+    while let Some(rest) = line.strip_prefix('{') {
+        match rest.split_once('}') {
+            Some((_, rest)) => line = rest,
+            None => break,
+        }
+    }
+    line.trim()
+}
+
+fn attribute<T: std::str::FromStr>(
+    node: Node<'_, '_>,
+    name: &'static str,
+) -> Result<Option<T>, PobError> {
+    node.attribute(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| PobError::InvalidAttribute(name, value.to_string()))
+        })
+        .transpose()
+}
+
+pub fn to_xml(build: &PobBuild) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<PathOfBuilding>\n");
+
+    xml.push_str("\t<Build");
+    for (name, value) in &build.build_attributes {
+        let _ = write!(xml, " {name}=\"{}\"", escape(value));
+    }
+    xml.push_str(">\n");
+    for child in &build.build_children {
+        let _ = writeln!(xml, "\t\t{child}");
+    }
+    xml.push_str("\t</Build>\n");
+
+    let nodes = build
+        .nodes
+        .iter()
+        .map(|node| node.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let mastery_effects = build
+        .mastery_effects
+        .iter()
+        .map(|(node, effect)| format!("{{{node},{effect}}}"))
+        .collect::<Vec<_>>()
+        .join(",");
+    let active_spec = build.active_spec.min(build.other_specs.len());
+    let _ = write!(xml, "\t<Tree activeSpec=\"{}\"", active_spec + 1);
+    for (name, value) in &build.tree_attributes {
+        let _ = write!(xml, " {name}=\"{}\"", escape(value));
+    }
+    xml.push_str(">\n");
+    for spec in &build.other_specs[..active_spec] {
+        let _ = writeln!(xml, "\t\t{spec}");
+    }
+    xml.push_str("\t\t<Spec");
+    for (name, value) in &build.spec_attributes {
+        let _ = write!(xml, " {name}=\"{}\"", escape(value));
+    }
+    let _ = writeln!(
+        xml,
+        " treeVersion=\"{}\" classId=\"{}\" ascendClassId=\"{}\" nodes=\"{nodes}\" masteryEffects=\"{mastery_effects}\">",
+        escape(&build.tree_version),
+        build.class_id,
+        build.ascendancy_id,
+    );
+    for child in &build.spec_children {
+        let _ = writeln!(xml, "\t\t\t{child}");
+    }
+    if !build.sockets.is_empty() {
+        xml.push_str("\t\t\t<Sockets>\n");
+        for (node, item) in &build.sockets {
+            let _ = writeln!(xml, "\t\t\t\t<Socket nodeId=\"{node}\" itemId=\"{item}\"/>");
+        }
+        xml.push_str("\t\t\t</Sockets>\n");
+    }
+    xml.push_str("\t\t</Spec>\n");
+    for spec in &build.other_specs[active_spec..] {
+        let _ = writeln!(xml, "\t\t{spec}");
+    }
+    xml.push_str("\t</Tree>\n");
+
+    let _ = writeln!(xml, "\t<Notes>{}</Notes>", escape(&build.notes));
+    if !build.items.is_empty() || !build.items_attributes.is_empty() {
+        xml.push_str("\t<Items");
+        for (name, value) in &build.items_attributes {
+            let _ = write!(xml, " {name}=\"{}\"", escape(value));
+        }
+        xml.push_str(">\n");
+        for item in &build.items {
+            let _ = writeln!(xml, "\t\t<Item id=\"{}\">\n{}", item.id, escape(&item.text));
+            for child in &item.children {
+                let _ = writeln!(xml, "\t\t\t{child}");
+            }
+            xml.push_str("\t\t</Item>\n");
+        }
+        for child in &build.items_children {
+            let _ = writeln!(xml, "\t\t{child}");
+        }
+        xml.push_str("\t</Items>\n");
+    }
+    for section in &build.sections {
+        let _ = writeln!(xml, "\t{section}");
+    }
+    xml.push_str("</PathOfBuilding>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_build() -> PobBuild {
+        PobBuild {
+            class_id: 3,
+            ascendancy_id: 2,
+            tree_version: "3_25".to_string(),
+            nodes: vec![54447, 5823, 37326],
+            mastery_effects: vec![(59920, 47642), (44298, 64370)],
+            notes: "Leveling: take <Elemental Overload> & \"Arcane Focus\" first".to_string(),
+            build_attributes: vec![
+                ("level".to_string(), "92".to_string()),
+                ("targetVersion".to_string(), "3_0".to_string()),
+                ("className".to_string(), "Witch".to_string()),
+            ],
+            build_children: vec![r#"<PlayerStat stat="Life" value="4321"/>"#.to_string()],
+            tree_attributes: vec![("showStatDifferences".to_string(), "true".to_string())],
+            spec_attributes: vec![("title".to_string(), "Endgame".to_string())],
+            spec_children: vec![r#"<EditedNodes/>"#.to_string()],
+            other_specs: vec![
+                r#"<Spec title="Leveling" treeVersion="3_25" nodes="54447"/>"#.to_string(),
+                r#"<Spec title="Budget" treeVersion="3_25" nodes="54447,5823"/>"#.to_string(),
+            ],
+            active_spec: 1,
+            sockets: vec![(26725, 1)],
+            items: vec![PobItem {
+                id: 1,
+                text:
+                    "Rarity: RARE\nGale Spark\nCobalt Jewel\nImplicits: 0\n+12% to Cold Resistance"
+                        .to_string(),
+                children: vec![],
+            }],
+            items_attributes: vec![("activeItemSet".to_string(), "1".to_string())],
+            items_children: vec![r#"<ItemSet id="1" useSecondWeaponSet="nil"/>"#.to_string()],
+            sections: vec![
+                r#"<Skills activeSkillSet="1"><SkillSet id="1"/></Skills>"#.to_string(),
+                r#"<Config><Input name="enemyIsBoss" string="Pinnacle"/></Config>"#.to_string(),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_through_xml() {
+        let build = sample_build();
+        assert_eq!(parse_xml(&to_xml(&build)).unwrap(), build);
+    }
+
+    #[test]
+    fn round_trips_through_code() {
+        let build = sample_build();
+        let code = encode(&build);
+        assert!(
+            !code.contains(['+', '/']),
+            "codes use the URL-safe alphabet"
+        );
+        assert_eq!(decode(&code).unwrap(), build);
+    }
+
+    const WITCH: &str = include_str!("../tests/fixtures/witch_elementalist.txt");
+    const JUGGERNAUT: &str = include_str!("../tests/fixtures/marauder_juggernaut.txt");
+
+    #[test]
+    fn decodes_active_spec() {
+        let witch = decode(WITCH).unwrap();
+        assert_eq!((witch.class_id, witch.ascendancy_id), (3, 2));
+        assert_eq!(witch.tree_version, "3_25");
+        assert_eq!(
+            witch.nodes,
+            [57226, 63965, 4367, 36634, 15144, 61981, 22618, 26725, 2151, 58032]
+        );
+        assert_eq!(witch.mastery_effects, [(22618, 48385)]);
+        assert_eq!(
+            witch.notes,
+            "Ignite league starter.\n^xE05030Swap to ^7Elemental Equilibrium at 90 & recheck resists."
+        );
+        // The empty socket's item id of 0 is dropped.
+        assert_eq!(witch.sockets, [(26725, 1)]);
+        assert_eq!(witch.items.len(), 1);
+
+        let juggernaut = decode(JUGGERNAUT).unwrap();
+        assert_eq!((juggernaut.class_id, juggernaut.ascendancy_id), (1, 1));
+        assert_eq!(juggernaut.nodes.len(), 8);
+        assert_eq!(juggernaut.mastery_effects, [(53802, 8833), (64355, 5495)]);
+        assert_eq!(juggernaut.notes, "");
+        assert_eq!(juggernaut.other_specs.len(), 1);
+        assert!(juggernaut.other_specs[0].contains(r#"title="Act 10""#));
+        assert_eq!(juggernaut.active_spec, 1);
+    }
+
+    #[test]
+    fn keeps_spec_order() {
+        let exported = to_xml(&sample_build());
+        assert!(exported.contains(r#"<Tree activeSpec="2" showStatDifferences="true">"#));
+        let titles: Vec<&str> = exported
+            .split("<Spec title=\"")
+            .skip(1)
+            .map(|spec| spec.split('"').next().unwrap())
+            .collect();
+        assert_eq!(titles, ["Leveling", "Endgame", "Budget"]);
+
+        let juggernaut = to_xml(&decode(JUGGERNAUT).unwrap());
+        assert!(juggernaut.contains(r#"<Tree activeSpec="2">"#));
+        assert!(juggernaut.find("Act 10").unwrap() < juggernaut.find("Maps").unwrap());
+        assert!(juggernaut.contains("<URL>https://www.pathofexile.com/passive-skill-tree/"));
+    }
+
+    #[test]
+    fn keeps_unknown_sections() {
+        for code in [WITCH, JUGGERNAUT] {
+            let build = decode(code).unwrap();
+            let exported = to_xml(&build);
+            for section in build
+                .sections
+                .iter()
+                .chain(&build.build_children)
+                .chain(&build.spec_children)
+                .chain(&build.other_specs)
+                .chain(&build.items_children)
+            {
+                assert!(exported.contains(section.as_str()), "lost {section}");
+            }
+            assert_eq!(decode(&encode(&build)).unwrap(), build);
+        }
+
+        let witch = decode(WITCH).unwrap();
+        let tags: Vec<&str> = witch
+            .sections
+            .iter()
+            .map(|section| section[1..].split([' ', '>']).next().unwrap())
+            .collect();
+        assert_eq!(tags, ["Import", "Calcs", "Skills", "TreeView", "Config"]);
+        assert_eq!(
+            witch.spec_attributes,
+            [
+                ("title".to_string(), "Mapping".to_string()),
+                ("secondaryAscendClassId".to_string(), "0".to_string()),
+            ]
+        );
+        assert!(witch.items[0].children[0].contains("ModRange"));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(matches!(decode("not a build!"), Err(PobError::Base64(_))));
+        assert!(matches!(
+            parse_xml("<PathOfBuilding><Build/></PathOfBuilding>"),
+            Err(PobError::MissingElement("Tree"))
+        ));
+    }
+
+    #[test]
+    fn reads_every_jewel_rarity() {
+        let rare = parse_jewel_item(
+            "Rarity: RARE\nGlyph Spark\nMurderous Eye Jewel\nItem Level: 84\nImplicits: 0\n\
+             {range:0.5}+8% to Fire Resistance",
+        )
+        .unwrap();
+        assert_eq!(rare.name, "Glyph Spark");
+        assert_eq!(rare.base, JewelBase::Abyss);
+        assert!(!rare.unique);
+        assert_eq!(rare.mods, ["+8% to Fire Resistance"]);
+
+        let magic = parse_jewel_item(
+            "Rarity: MAGIC\nVivid Cobalt Jewel of the Wolf\nImplicits: 0\n\
+             +10 to Intelligence\n8% increased Spell Damage",
+        )
+        .unwrap();
+        assert_eq!(magic.name, "Vivid Cobalt Jewel of the Wolf");
+        assert_eq!(magic.base, JewelBase::Cobalt);
+        assert!(!magic.unique);
+        assert_eq!(magic.mods.len(), 2);
+
+        let normal = parse_jewel_item("Rarity: NORMAL\nCrimson Jewel\nImplicits: 0").unwrap();
+        assert_eq!(normal.base, JewelBase::Crimson);
+        assert!(normal.mods.is_empty());
+
+        let unique = parse_jewel_item(
+            "Rarity: UNIQUE\nWatcher's Eye\nPrismatic Jewel\nLimited to: 1\nImplicits: 0\n\
+             4% increased maximum Energy Shield",
+        )
+        .unwrap();
+        assert!(unique.unique);
+        assert_eq!(unique.mods, ["4% increased maximum Energy Shield"]);
+
+        assert!(parse_jewel_item("Rarity: MAGIC\nSerrated Ruby Ring of the Bear").is_none());
+        assert!(parse_jewel_item("Rarity: RARE\nDoom Loop\nTwo-Stone Ring").is_none());
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let jewel = parse_jewel_item(
+            "Rarity: RARE\nEntropy Hope\nViridian Jewel\nImplicits: 0\n\
+             7% increased Attack Speed\n+12% to Global Critical Strike Multiplier",
+        )
+        .unwrap();
+        assert_eq!(parse_jewel_item(&jewel_item(&jewel)), Some(jewel));
+    }
+}
@@ -0,0 +1,123 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::graph::{NodeIndex, TreeGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Case-insensitive plain text.
+    #[default]
+    Substring,
+    /// A regular expression, case-sensitive unless it asks otherwise with `(?i)`.
+    Regex,
+}
+
+enum Matcher {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(needle) => text.to_lowercase().contains(needle),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Every named node whose name or stat text matches `query`, in index order.
+pub fn search(
+    graph: &TreeGraph,
+    query: &str,
+    mode: SearchMode,
+) -> Result<Vec<NodeIndex>, regex::Error> {
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let matcher = match mode {
+        SearchMode::Substring => Matcher::Substring(query.to_lowercase()),
+        SearchMode::Regex => Matcher::Regex(RegexBuilder::new(query).size_limit(1 << 20).build()?),
+    };
+
+    Ok(graph
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| !node.is_proxy.unwrap_or(false))
+        .filter(|(_, node)| {
+            node.name
+                .as_deref()
+                .is_some_and(|name| matcher.is_match(name))
+                || node.stats.iter().any(|stat| matcher.is_match(stat))
+        })
+        .map(|(index, _)| index)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::tree::Node;
+
+    fn graph() -> TreeGraph {
+        let node = |skill: usize, name: &str, stats: &[&str]| {
+            let node = Node {
+                skill: Some(skill),
+                name: Some(name.to_string()),
+                stats: stats.iter().map(|line| line.to_string()).collect(),
+                is_proxy: Some(skill == 4),
+                ..Default::default()
+            };
+            (skill.to_string(), node)
+        };
+        let nodes = [
+            node(
+                1,
+                "Heart of Oak",
+                &["+20 to maximum Life", "5% increased Life Regeneration"],
+            ),
+            node(2, "Lethality", &["25% increased Critical Strike Chance"]),
+            node(3, "Life Raker", &["Adds 2 to 4 Physical Damage"]),
+            node(4, "Life Proxy", &[]),
+        ];
+        TreeGraph::new(nodes.into_iter().collect(), HashMap::new())
+    }
+
+    fn skills(graph: &TreeGraph, query: &str, mode: SearchMode) -> Vec<usize> {
+        search(graph, query, mode)
+            .unwrap()
+            .into_iter()
+            .map(|index| graph.skill(index))
+            .collect()
+    }
+
+    #[test]
+    fn substring_ignores_case_and_checks_stats() {
+        let graph = graph();
+        assert_eq!(skills(&graph, "LIFE", SearchMode::Substring), [1, 3]);
+        assert_eq!(skills(&graph, "critical", SearchMode::Substring), [2]);
+        assert_eq!(skills(&graph, "oak", SearchMode::Substring), [1]);
+        assert!(skills(&graph, "mana", SearchMode::Substring).is_empty());
+        assert!(skills(&graph, "", SearchMode::Substring).is_empty());
+        // Regex syntax is plain text here.
+        assert!(skills(&graph, "^L", SearchMode::Substring).is_empty());
+    }
+
+    #[test]
+    fn regex_is_case_sensitive_unless_asked() {
+        let graph = graph();
+        assert_eq!(skills(&graph, "^L", SearchMode::Regex), [2, 3]);
+        assert!(skills(&graph, "^l", SearchMode::Regex).is_empty());
+        assert_eq!(skills(&graph, "(?i)^l", SearchMode::Regex), [2, 3]);
+        assert_eq!(skills(&graph, r"\d+% increased", SearchMode::Regex), [1, 2]);
+    }
+
+    #[test]
+    fn reports_bad_regexes() {
+        let graph = graph();
+        assert!(search(&graph, "(unclosed", SearchMode::Regex).is_err());
+        assert!(search(&graph, "(unclosed", SearchMode::Substring).is_ok());
+    }
+}
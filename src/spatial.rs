@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use egui::Pos2;
+
+use crate::{
+    graph::{NodeIndex, TreeGraph},
+    tree::Node,
+};
+
+/// Side of a grid cell in tree units, a bit over the widest keystone frame.
+const CELL_SIZE: f32 = 256.0;
+
+/// Node positions bucketed into a uniform grid, so finding the node under the pointer only
+/// looks at the few cells around it. Rebuild it whenever the graph's nodes change.
+#[derive(Default)]
+pub struct NodeGrid {
+    cells: HashMap<(i32, i32), Vec<NodeIndex>>,
+    /// Position and hit radius of every node, `None` for nodes that can't be hit.
+    targets: Vec<Option<(Pos2, f32)>>,
+    max_radius: f32,
+}
+
+impl NodeGrid {
+    /// `radius` gives how far from its center a node can be hit, in tree units, or `None` to
+    /// leave it out.
+    pub fn new(graph: &TreeGraph, radius: impl Fn(&Node) -> Option<f32>) -> Self {
+        let mut grid = Self::default();
+        for (index, node) in graph.nodes.iter().enumerate() {
+            let target = radius(node).map(|radius| (graph.positions[index], radius));
+            if let Some((position, radius)) = target {
+                grid.cells.entry(cell_of(position)).or_default().push(index);
+                grid.max_radius = grid.max_radius.max(radius);
+            }
+            grid.targets.push(target);
+        }
+        grid
+    }
+
+    /// The node nearest to `world` among those whose hit radius reaches it.
+    pub fn node_at(&self, world: Pos2) -> Option<NodeIndex> {
+        let (min_x, min_y) = cell_of(world - egui::Vec2::splat(self.max_radius));
+        let (max_x, max_y) = cell_of(world + egui::Vec2::splat(self.max_radius));
+        (min_x..=max_x)
+            .flat_map(|x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter_map(|&index| {
+                let (position, radius) = self.targets[index]?;
+                let distance = position.distance(world);
+                (distance <= radius).then_some((index, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+}
+
+fn cell_of(position: Pos2) -> (i32, i32) {
+    (
+        (position.x / CELL_SIZE).floor() as i32,
+        (position.y / CELL_SIZE).floor() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::pos2;
+
+    use super::*;
+    use crate::tree::Group;
+
+    /// Small deterministic generator, so failures reproduce.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, range: f32) -> f32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32 * range
+        }
+    }
+
+    fn radius(node: &Node) -> Option<f32> {
+        match node.skill? % 5 {
+            0 => None,
+            n => Some(n as f32 * 45.0),
+        }
+    }
+
+    /// Each node gets its own group on orbit 0, so it sits right on the group's position.
+    fn graph(positions: &[Pos2]) -> TreeGraph {
+        let mut nodes = HashMap::new();
+        let mut groups = HashMap::new();
+        for (index, position) in positions.iter().enumerate() {
+            let id = index + 1;
+            let node = Node {
+                skill: Some(id),
+                group: Some(id),
+                orbit: Some(0),
+                ..Default::default()
+            };
+            nodes.insert(id.to_string(), node);
+            let group = Group {
+                x: position.x,
+                y: position.y,
+                orbits: vec![0],
+                ..Default::default()
+            };
+            groups.insert(id.to_string(), group);
+        }
+        TreeGraph::new(nodes, groups)
+    }
+
+    fn brute_force(graph: &TreeGraph, world: Pos2) -> Option<f32> {
+        graph
+            .nodes
+            .iter()
+            .zip(&graph.positions)
+            .filter_map(|(node, position)| {
+                let distance = position.distance(world);
+                (distance <= radius(node)?).then_some(distance)
+            })
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn matches_a_brute_force_scan() {
+        let mut random = Lcg(7);
+        let mut positions: Vec<Pos2> = (0..400)
+            .map(|_| pos2(random.next(4096.0) - 2048.0, random.next(4096.0) - 2048.0))
+            .collect();
+        // Nodes right on cell borders and corners, on both sides of zero.
+        for x in [-512.0, -256.0, 0.0, 256.0, 768.0] {
+            positions.push(pos2(x, 100.0));
+            positions.push(pos2(-100.0, x));
+            positions.push(pos2(x, x));
+        }
+        let graph = graph(&positions);
+        let grid = NodeGrid::new(&graph, radius);
+
+        let mut queries: Vec<Pos2> = (0..5000)
+            .map(|_| pos2(random.next(5000.0) - 2500.0, random.next(5000.0) - 2500.0))
+            .collect();
+        for step in -12..=12 {
+            let border = step as f32 * CELL_SIZE;
+            for offset in [-0.5, 0.0, 0.5, 44.9, 180.0] {
+                queries.push(pos2(border, border + offset));
+                queries.push(pos2(border - offset, 30.0));
+                queries.push(pos2(-30.0, border + offset));
+            }
+        }
+        // Just inside and outside the reach of a few nodes.
+        for &position in positions.iter().take(20) {
+            queries.push(position + egui::vec2(0.0, 44.99));
+            queries.push(position + egui::vec2(180.01, 0.0));
+        }
+
+        let mut hits = 0;
+        for query in queries {
+            let found = grid
+                .node_at(query)
+                .map(|index| graph.positions[index].distance(query));
+            assert_eq!(found, brute_force(&graph, query), "at {query:?}");
+            hits += found.is_some() as usize;
+        }
+        assert!(hits > 100, "only {hits} queries hit a node");
+    }
+
+    #[test]
+    fn leaves_out_nodes_without_a_radius() {
+        let graph = graph(&[pos2(10.0, 10.0), pos2(0.0, 0.0)]);
+        let grid = NodeGrid::new(&graph, |node| (node.skill != Some(1)).then_some(50.0));
+        assert_eq!(grid.node_at(pos2(10.0, 10.0)), graph.index_of(2));
+        assert_eq!(grid.node_at(pos2(60.0, 0.0)), None);
+        assert_eq!(NodeGrid::default().node_at(pos2(0.0, 0.0)), None);
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    build::Build,
+    graph::TreeGraph,
+    jewel::{Jewel, StatTransform},
+    modifiers::{self, ModifierKind},
+    timeless::{TimelessTables, TimelessTransform},
+    tree::{Class, TreeExport},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Attributes,
+    LifeManaEnergyShield,
+    Resistances,
+    Defences,
+    Damage,
+    Speed,
+    Critical,
+    Other,
+    Keystones,
+}
+
+impl Category {
+    fn of(stat: &str, kind: ModifierKind) -> Self {
+        let has = |word: &str| stat.split('_').any(|part| part == word);
+        if kind == ModifierKind::Flag {
+            Self::Keystones
+        } else if has("strength") || has("dexterity") || has("intelligence") || has("attributes") {
+            Self::Attributes
+        } else if has("resistance") || has("resistances") {
+            Self::Resistances
+        } else if has("life") || has("mana") || stat.contains("energy_shield") {
+            Self::LifeManaEnergyShield
+        } else if has("armour") || has("evasion") || has("block") || has("suppress") {
+            Self::Defences
+        } else if has("critical") {
+            Self::Critical
+        } else if has("damage") {
+            Self::Damage
+        } else if has("speed") {
+            Self::Speed
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Attributes => "Attributes",
+            Self::LifeManaEnergyShield => "Life, Mana & Energy Shield",
+            Self::Resistances => "Resistances",
+            Self::Defences => "Defences",
+            Self::Damage => "Damage",
+            Self::Speed => "Speed",
+            Self::Critical => "Critical Strikes",
+            Self::Other => "Other",
+            Self::Keystones => "Keystones & Effects",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Every modifier sharing a stat, kind and set of conditions, combined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Total {
+    pub stat: String,
+    pub kind: ModifierKind,
+    pub conditions: Vec<String>,
+    /// Summed, except for "more" modifiers which multiply.
+    pub value: f32,
+    /// The first stat line that contributed, shown as-is for flags.
+    pub text: String,
+}
+
+impl fmt::Display for Total {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Hides float noise from summing and multiplying, e.g. 21.000002% more.
+        let value = (self.value * 100.0).round() / 100.0;
+        let (name, percent) = match self.stat.strip_suffix("_percent") {
+            Some(name) => (name.replace('_', " "), "%"),
+            None => (self.stat.replace('_', " "), ""),
+        };
+        match self.kind {
+            ModifierKind::Increased if value < 0.0 => write!(f, "{}% reduced {name}", -value),
+            ModifierKind::Increased => write!(f, "{value}% increased {name}"),
+            ModifierKind::More if value < 0.0 => write!(f, "{}% less {name}", -value),
+            ModifierKind::More => write!(f, "{value}% more {name}"),
+            ModifierKind::Flat => write!(f, "{value:+}{percent} to {name}"),
+            ModifierKind::Base if name.starts_with("chance to") => write!(f, "{value}% {name}"),
+            ModifierKind::Base => write!(f, "{value}{percent} {name}"),
+            ModifierKind::Flag => f.write_str(&self.text),
+        }?;
+        for condition in &self.conditions {
+            write!(f, " {condition}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StatSummary {
+    pub categories: BTreeMap<Category, Vec<Total>>,
+    /// Stat lines of allocated nodes that the modifier parser didn't understand.
+    pub unparsed: Vec<String>,
+}
+
+/// Adds up the stats of every allocated node, chosen mastery effect and socketed jewel.
+/// Timeless jewels only change their radius when their lookup table is in `timeless`.
+pub fn summarize(graph: &TreeGraph, build: &Build, timeless: &TimelessTables) -> StatSummary {
+    let transformed = transformed_stats(graph, build, timeless);
+    let mut parsed = modifiers::ParsedStats::default();
+    for &skill in &build.allocated {
+        let stats = match (transformed.get(&skill), graph.node(skill)) {
+            (Some(stats), _) => stats,
+            (None, Some(node)) => &node.stats,
+            (None, None) => continue,
+        };
+        parsed.extend(modifiers::parse_stats(stats));
+    }
+    for (&mastery, &effect) in &build.mastery_effects {
+        let chosen = graph.node(mastery).and_then(|node| {
+            node.mastery_effects
+                .iter()
+                .find(|option| option.effect == effect)
+        });
+        if let Some(chosen) = chosen {
+            parsed.extend(modifiers::parse_stats(&chosen.stats));
+        }
+    }
+    for (_, jewel) in build
+        .jewels
+        .iter()
+        .filter(|(socket, _)| build.is_allocated(**socket))
+    {
+        parsed.extend(modifiers::parse_stats(&jewel.mods));
+    }
+
+    let mut totals: Vec<Total> = vec![];
+    for modifier in parsed.modifiers {
+        let existing = totals.iter_mut().find(|total| {
+            total.stat == modifier.stat
+                && total.kind == modifier.kind
+                && total.conditions == modifier.conditions
+        });
+        match existing {
+            Some(total) => match modifier.kind {
+                ModifierKind::More => {
+                    total.value = ((1.0 + total.value / 100.0) * (1.0 + modifier.value / 100.0)
+                        - 1.0)
+                        * 100.0;
+                }
+                ModifierKind::Flag => {}
+                _ => total.value += modifier.value,
+            },
+            None => totals.push(Total {
+                stat: modifier.stat,
+                kind: modifier.kind,
+                conditions: modifier.conditions,
+                value: modifier.value,
+                text: modifier.text,
+            }),
+        }
+    }
+
+    let mut summary = StatSummary {
+        unparsed: parsed.unparsed,
+        ..Default::default()
+    };
+    totals.sort_by(|a, b| (&a.stat, &a.conditions).cmp(&(&b.stat, &b.conditions)));
+    for total in totals {
+        summary
+            .categories
+            .entry(Category::of(&total.stat, total.kind))
+            .or_default()
+            .push(total);
+    }
+    summary
+}
+
+/// The last summary and what it was added up from, so it's only redone when the build changes.
+#[derive(Default)]
+pub struct StatCache {
+    tree: String,
+    allocated: HashSet<usize>,
+    mastery_effects: HashMap<usize, usize>,
+    jewels: HashMap<usize, Jewel>,
+    summary: Option<StatSummary>,
+}
+
+impl StatCache {
+    pub fn summary(
+        &mut self,
+        tree: &TreeExport,
+        build: &Build,
+        timeless: &TimelessTables,
+    ) -> &StatSummary {
+        let stale = self.tree != tree.tree
+            || self.allocated != build.allocated
+            || self.mastery_effects != build.mastery_effects
+            || self.jewels != build.jewels;
+        if stale || self.summary.is_none() {
+            self.tree.clone_from(&tree.tree);
+            self.allocated.clone_from(&build.allocated);
+            self.mastery_effects.clone_from(&build.mastery_effects);
+            self.jewels.clone_from(&build.jewels);
+            self.summary = Some(summarize(&tree.graph, build, timeless));
+        }
+        self.summary.get_or_insert_with(Default::default)
+    }
+
+    /// Adds the summary up again next time, for changes the build doesn't show, like the tree
+    /// being replaced by another of the same version.
+    pub fn invalidate(&mut self) {
+        self.summary = None;
+    }
+}
+
+/// Stats of allocated passives that socketed jewels rewrite, keyed by skill. Passives no jewel
+/// touches keep their own stats and aren't included.
+fn transformed_stats(
+    graph: &TreeGraph,
+    build: &Build,
+    timeless: &TimelessTables,
+) -> HashMap<usize, Vec<String>> {
+    let mut transformed: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut sockets: Vec<usize> = build.jewels.keys().copied().collect();
+    // Jewels overlapping on a passive apply in a stable order.
+    sockets.sort_unstable();
+    for socket in sockets
+        .into_iter()
+        .filter(|&socket| build.is_allocated(socket))
+    {
+        let jewel = &build.jewels[&socket];
+        let mut transforms: Vec<Box<dyn StatTransform + '_>> = jewel.transforms();
+        // Timeless jewels replace stats outright, so they go before anything that edits them.
+        if let Some((table, seed)) = timeless.lookup(jewel) {
+            transforms.insert(0, Box::new(TimelessTransform { table, seed }));
+        }
+        if transforms.is_empty() {
+            continue;
+        }
+        for index in jewel.affected_nodes(graph, socket) {
+            let node = &graph.nodes[index];
+            let skill = graph.skill(index);
+            if !build.is_allocated(skill) {
+                continue;
+            }
+            let stats = transformed
+                .entry(skill)
+                .or_insert_with(|| node.stats.clone());
+            for transform in &transforms {
+                transform.apply(node, stats);
+            }
+        }
+    }
+    transformed
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub strength: u32,
+    pub dexterity: u32,
+    pub intelligence: u32,
+}
+
+/// The class's starting attributes plus every unconditional flat and increased attribute total
+/// in `summary`. Increases apply after flat bonuses and the result is rounded down, as in game.
+pub fn attributes(class: &Class, summary: &StatSummary) -> Attributes {
+    let totals = summary
+        .categories
+        .get(&Category::Attributes)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let sum = |kind: ModifierKind, stats: &[&str]| -> f32 {
+        totals
+            .iter()
+            .filter(|total| {
+                total.kind == kind
+                    && total.conditions.is_empty()
+                    && stats.contains(&total.stat.as_str())
+            })
+            .map(|total| total.value)
+            .sum()
+    };
+    let total = |base: u32, stat: &str| {
+        let flat = base as f32 + sum(ModifierKind::Flat, &[stat]);
+        let increased = sum(ModifierKind::Increased, &[stat, "attributes"]);
+        (flat * (1.0 + increased / 100.0)).max(0.0) as u32
+    };
+    Attributes {
+        strength: total(class.base_str, "strength"),
+        dexterity: total(class.base_dex, "dexterity"),
+        intelligence: total(class.base_int, "intelligence"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(nodes: &[(usize, &[&str])]) -> TreeGraph {
+        let nodes = nodes
+            .iter()
+            .map(|&(skill, stats)| {
+                let node = crate::tree::Node {
+                    skill: Some(skill),
+                    stats: stats.iter().map(|line| line.to_string()).collect(),
+                    ..Default::default()
+                };
+                (skill.to_string(), node)
+            })
+            .collect();
+        TreeGraph::new(nodes, HashMap::new())
+    }
+
+    fn allocated(skills: &[usize]) -> Build {
+        Build {
+            allocated: skills.iter().copied().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn adds_attributes_to_the_class_base() {
+        let graph = graph(&[
+            (1, &["+10 to Strength"]),
+            (2, &["+20 to all Attributes"]),
+            (3, &["+16 to Dexterity and Intelligence"]),
+            (4, &["+30 to Intelligence"]),
+            (5, &["+10 to Strength while Dual Wielding"]),
+            (6, &["10% increased Strength"]),
+        ]);
+        let marauder = Class {
+            name: "Marauder".to_string(),
+            base_str: 32,
+            base_dex: 14,
+            base_int: 14,
+            ..Default::default()
+        };
+        let timeless = TimelessTables::default();
+
+        let summary = summarize(&graph, &allocated(&[]), &timeless);
+        assert_eq!(
+            attributes(&marauder, &summary),
+            Attributes {
+                strength: 32,
+                dexterity: 14,
+                intelligence: 14,
+            }
+        );
+
+        // Conditional bonuses don't count, the increase applies after every flat bonus.
+        let summary = summarize(&graph, &allocated(&[1, 2, 3, 5]), &timeless);
+        assert_eq!(
+            attributes(&marauder, &summary),
+            Attributes {
+                strength: 62,
+                dexterity: 50,
+                intelligence: 50,
+            }
+        );
+        let summary = summarize(&graph, &allocated(&[1, 2, 6]), &timeless);
+        assert_eq!(attributes(&marauder, &summary).strength, 68);
+    }
+
+    #[test]
+    fn sums_a_stat_across_nodes() {
+        let graph = graph(&[
+            (1, &["10% increased Attack Speed", "+10 to maximum Life"]),
+            (2, &["6% increased Attack Speed"]),
+            (3, &["4% reduced Attack Speed", "20% more Damage"]),
+            (4, &["10% more Damage"]),
+            (5, &["8% increased Attack Speed while Dual Wielding"]),
+        ]);
+        let summary = summarize(
+            &graph,
+            &allocated(&[1, 2, 3, 4, 5]),
+            &TimelessTables::default(),
+        );
+        let speed = &summary.categories[&Category::Speed];
+        assert_eq!(speed.len(), 2);
+        assert_eq!(speed[0].value, 12.0);
+        assert_eq!(speed[0].to_string(), "12% increased attack speed");
+        assert_eq!(speed[1].conditions, ["while Dual Wielding"]);
+
+        // More multipliers compound rather than add.
+        let damage = &summary.categories[&Category::Damage];
+        assert_eq!(damage[0].to_string(), "32% more damage");
+        assert_eq!(
+            summary.categories[&Category::LifeManaEnergyShield][0].value,
+            10.0
+        );
+    }
+
+    #[test]
+    fn cache_follows_the_build() {
+        let tree = TreeExport {
+            graph: graph(&[(1, &["+10 to Strength"]), (2, &["+5 to Strength"])]),
+            ..Default::default()
+        };
+        let timeless = TimelessTables::default();
+        let strength = |summary: &StatSummary| summary.categories[&Category::Attributes][0].value;
+        let mut cache = StatCache::default();
+        let mut build = allocated(&[1]);
+        assert_eq!(strength(cache.summary(&tree, &build, &timeless)), 10.0);
+
+        build.allocated.insert(2);
+        assert_eq!(strength(cache.summary(&tree, &build, &timeless)), 15.0);
+
+        build.allocated.remove(&1);
+        build
+            .jewels
+            .insert(1, Jewel::new(crate::jewel::JewelBase::Crimson));
+        assert_eq!(strength(cache.summary(&tree, &build, &timeless)), 5.0);
+    }
+}
@@ -0,0 +1,509 @@
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build::Build,
+    graph::{NodeIndex, TreeGraph},
+    jewel::{Jewel, StatTransform},
+    tree::Node,
+};
+
+/// Where the lookup tables are read from, named after `TimelessKind::file_stem`.
+#[cfg(not(target_arch = "wasm32"))]
+pub const TIMELESS_DIR: &str = "resources/timeless";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimelessKind {
+    GloriousVanity,
+    LethalPride,
+    BrutalRestraint,
+    MilitantFaith,
+    ElegantHubris,
+}
+
+impl TimelessKind {
+    pub const ALL: [TimelessKind; 5] = [
+        Self::GloriousVanity,
+        Self::LethalPride,
+        Self::BrutalRestraint,
+        Self::MilitantFaith,
+        Self::ElegantHubris,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::GloriousVanity => "Glorious Vanity",
+            Self::LethalPride => "Lethal Pride",
+            Self::BrutalRestraint => "Brutal Restraint",
+            Self::MilitantFaith => "Militant Faith",
+            Self::ElegantHubris => "Elegant Hubris",
+        }
+    }
+
+    pub fn file_stem(&self) -> &'static str {
+        match self {
+            Self::GloriousVanity => "glorious_vanity",
+            Self::LethalPride => "lethal_pride",
+            Self::BrutalRestraint => "brutal_restraint",
+            Self::MilitantFaith => "militant_faith",
+            Self::ElegantHubris => "elegant_hubris",
+        }
+    }
+
+    pub fn conquerors(&self) -> [&'static str; 4] {
+        match self {
+            Self::GloriousVanity => ["Xibaqua", "Zerphi", "Ahuana", "Doryani"],
+            Self::LethalPride => ["Kaom", "Rakiok", "Kiloava", "Akoya"],
+            Self::BrutalRestraint => ["Balbala", "Asenath", "Nasima", "Deshret"],
+            Self::MilitantFaith => ["Avarius", "Dominus", "Maxarius", "Venarius"],
+            Self::ElegantHubris => ["Cadiro", "Victario", "Chitus", "Caspiro"],
+        }
+    }
+
+    /// Seeds the jewel can roll, inclusive.
+    pub fn seeds(&self) -> (u32, u32) {
+        match self {
+            Self::GloriousVanity => (100, 8000),
+            Self::LethalPride => (10000, 18000),
+            Self::BrutalRestraint => (500, 8000),
+            Self::MilitantFaith => (2000, 10000),
+            Self::ElegantHubris => (2000, 160000),
+        }
+    }
+
+    /// Elegant Hubris only rolls multiples of 20.
+    pub fn seed_step(&self) -> u32 {
+        match self {
+            Self::ElegantHubris => 20,
+            _ => 1,
+        }
+    }
+
+    /// The nearest seed the jewel can actually roll.
+    pub fn snap_seed(&self, seed: u32) -> u32 {
+        let (min, max) = self.seeds();
+        let step = self.seed_step();
+        let snapped = (seed.clamp(min, max) - min + step / 2) / step * step + min;
+        snapped.min(max)
+    }
+
+    /// The jewel's seed mod, with `{seed}` and `{conqueror}` standing in for the values.
+    fn mod_template(&self) -> &'static str {
+        match self {
+            Self::GloriousVanity => {
+                "Bathed in the blood of {seed} sacrificed in the name of {conqueror}"
+            }
+            Self::LethalPride => "Commanded leadership over {seed} warriors under {conqueror}",
+            Self::BrutalRestraint => {
+                "Denoted service of {seed} dekhara in the akhara of {conqueror}"
+            }
+            Self::MilitantFaith => {
+                "Carved to glorify {seed} new faithful converted by High Templar {conqueror}"
+            }
+            Self::ElegantHubris => "Commissioned {seed} coins to commemorate {conqueror}",
+        }
+    }
+}
+
+impl fmt::Display for TimelessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a timeless jewel rolled, as read from its seed mod.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelessSeed {
+    pub kind: TimelessKind,
+    pub seed: u32,
+    pub conqueror: String,
+}
+
+// One pattern per kind, built from the mod templates on first use.
+fn seed_patterns() -> &'static [(TimelessKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(TimelessKind, Regex)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        TimelessKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let pattern = regex::escape(kind.mod_template())
+                    .replace(r"\{seed\}", r"(\d+)")
+                    .replace(r"\{conqueror\}", r"(\w+)");
+                let regex = Regex::new(&format!("^{pattern}$")).expect("seed patterns are valid");
+                (kind, regex)
+            })
+            .collect()
+    })
+}
+
+impl TimelessSeed {
+    /// Finds the seed mod among a jewel's mods.
+    pub fn from_mods(mods: &[String]) -> Option<Self> {
+        mods.iter().find_map(|line| {
+            seed_patterns().iter().find_map(|(kind, regex)| {
+                let captures = regex.captures(line)?;
+                Some(Self {
+                    kind: *kind,
+                    seed: captures[1].parse().ok()?,
+                    conqueror: captures[2].to_string(),
+                })
+            })
+        })
+    }
+
+    pub fn mod_line(&self) -> String {
+        self.kind
+            .mod_template()
+            .replace("{seed}", &self.seed.to_string())
+            .replace("{conqueror}", &self.conqueror)
+    }
+
+    /// Replaces the seed mod in `jewel`, or adds one.
+    pub fn apply_to(&self, jewel: &mut Jewel) {
+        jewel
+            .mods
+            .retain(|line| Self::from_mods(&[line.clone()]).is_none());
+        jewel.mods.insert(0, self.mod_line());
+    }
+}
+
+/// What a passive becomes under a timeless jewel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replacement {
+    pub name: String,
+    #[serde(default)]
+    pub stats: Vec<String>,
+}
+
+/// The passives a timeless jewel replaces for every seed, generated ahead of time and read from
+/// one JSON file per jewel:
+///
+/// ```json
+/// {
+///   "conquerors": { "Xibaqua": { "name": "Divine Flesh", "stats": ["..."] } },
+///   "seeds": { "100": { "4367": { "name": "Ritual of Flesh", "stats": ["..."] } } }
+/// }
+/// ```
+///
+/// Replacements hold the complete stats the passive ends up with, additions included.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LookupTable {
+    /// The keystone each conqueror turns keystones into.
+    #[serde(default)]
+    pub conquerors: HashMap<String, Replacement>,
+    /// Per seed, the replacement for every passive it changes, keyed by skill id.
+    pub seeds: HashMap<u32, HashMap<usize, Replacement>>,
+}
+
+impl LookupTable {
+    pub fn from_slice(data: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(data)
+    }
+
+    /// What `node` turns into under `seed` and `conqueror`, if the jewel changes it. Keystones
+    /// depend only on the conqueror.
+    pub fn replacement(&self, seed: u32, conqueror: &str, node: &Node) -> Option<&Replacement> {
+        if node.is_keystone {
+            return self.conquerors.get(conqueror);
+        }
+        self.seeds.get(&seed)?.get(&node.skill?)
+    }
+}
+
+#[derive(Debug)]
+pub enum TimelessError {
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(std::path::PathBuf, std::io::Error),
+    Parse(TimelessKind, serde_json::Error),
+}
+
+impl fmt::Display for TimelessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Io(path, e) => write!(f, "could not read {}: {e}", path.display()),
+            Self::Parse(kind, e) => write!(f, "{kind} lookup table is invalid: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TimelessError {}
+
+/// Every lookup table that could be loaded. Jewels without one leave their radius untouched.
+#[derive(Default)]
+pub struct TimelessTables {
+    tables: HashMap<TimelessKind, LookupTable>,
+}
+
+impl TimelessTables {
+    /// Reads whichever tables exist in `dir`. Missing files are skipped, broken ones reported.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_dir(dir: impl AsRef<std::path::Path>) -> (Self, Vec<TimelessError>) {
+        let mut tables = Self::default();
+        let mut errors = vec![];
+        for kind in TimelessKind::ALL {
+            let path = dir.as_ref().join(format!("{}.json", kind.file_stem()));
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    errors.push(TimelessError::Io(path, e));
+                    continue;
+                }
+            };
+            match LookupTable::from_slice(&data) {
+                Ok(table) => {
+                    tables.tables.insert(kind, table);
+                }
+                Err(e) => errors.push(TimelessError::Parse(kind, e)),
+            }
+        }
+        (tables, errors)
+    }
+
+    pub fn get(&self, kind: TimelessKind) -> Option<&LookupTable> {
+        self.tables.get(&kind)
+    }
+
+    /// The table and seed for `jewel`, if it's a timeless jewel whose table is loaded.
+    pub fn lookup(&self, jewel: &Jewel) -> Option<(&LookupTable, TimelessSeed)> {
+        let seed = TimelessSeed::from_mods(&jewel.mods)?;
+        Some((self.get(seed.kind)?, seed))
+    }
+}
+
+/// What a timeless jewel in an allocated socket turns the passive at `index` into, if one
+/// reaches it.
+pub fn replacement_at<'a>(
+    tables: &'a TimelessTables,
+    graph: &TreeGraph,
+    build: &Build,
+    index: NodeIndex,
+) -> Option<&'a Replacement> {
+    let mut sockets: Vec<usize> = build.jewels.keys().copied().collect();
+    sockets.sort_unstable();
+    sockets
+        .into_iter()
+        .filter(|&socket| build.is_allocated(socket))
+        .find_map(|socket| {
+            let jewel = &build.jewels[&socket];
+            let (table, seed) = tables.lookup(jewel)?;
+            if !jewel.affected_nodes(graph, socket).contains(&index) {
+                return None;
+            }
+            table.replacement(seed.seed, &seed.conqueror, &graph.nodes[index])
+        })
+}
+
+/// Replaces the stats of passives in a timeless jewel's radius with the table's.
+pub struct TimelessTransform<'a> {
+    pub table: &'a LookupTable,
+    pub seed: TimelessSeed,
+}
+
+impl StatTransform for TimelessTransform<'_> {
+    fn apply(&self, node: &Node, stats: &mut Vec<String>) {
+        let seed = &self.seed;
+        if let Some(replacement) = self.table.replacement(seed.seed, &seed.conqueror, node) {
+            stats.clone_from(&replacement.stats);
+        }
+    }
+}
+
+/// How well a seed fits a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedMatch {
+    pub seed: u32,
+    /// How many replaced stat lines matched each wanted stat, in the order they were asked for.
+    pub counts: Vec<usize>,
+}
+
+impl SeedMatch {
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// Every seed that gives at least one of each `wanted` stat across `nodes`, best first. Stats
+/// match case-insensitively on part of the line, so `"attack speed"` finds any attack speed mod.
+pub fn search_seeds(
+    table: &LookupTable,
+    graph: &TreeGraph,
+    nodes: &[NodeIndex],
+    conqueror: &str,
+    wanted: &[String],
+) -> Vec<SeedMatch> {
+    let wanted: Vec<String> = wanted.iter().map(|stat| stat.to_lowercase()).collect();
+    if wanted.is_empty() {
+        return vec![];
+    }
+    let mut matches: Vec<SeedMatch> = table
+        .seeds
+        .keys()
+        .filter_map(|&seed| {
+            let mut counts = vec![0; wanted.len()];
+            for &index in nodes {
+                let Some(replacement) = table.replacement(seed, conqueror, &graph.nodes[index])
+                else {
+                    continue;
+                };
+                for stat in &replacement.stats {
+                    let stat = stat.to_lowercase();
+                    for (count, wanted) in counts.iter_mut().zip(&wanted) {
+                        if stat.contains(wanted.as_str()) {
+                            *count += 1;
+                        }
+                    }
+                }
+            }
+            counts
+                .iter()
+                .all(|&count| count > 0)
+                .then_some(SeedMatch { seed, counts })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.total().cmp(&a.total()).then(a.seed.cmp(&b.seed)));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const FIXTURES: &str = "tests/fixtures/timeless";
+
+    fn node(skill: usize, keystone: bool) -> (String, Node) {
+        let node = Node {
+            skill: Some(skill),
+            name: Some(format!("Passive {skill}")),
+            is_keystone: keystone,
+            stats: vec!["+10 to Strength".to_string()],
+            ..Default::default()
+        };
+        (skill.to_string(), node)
+    }
+
+    /// Two small passives the table replaces, one it doesn't and a keystone.
+    fn graph() -> TreeGraph {
+        let nodes = [
+            node(1, false),
+            node(2, false),
+            node(3, false),
+            node(4, true),
+        ];
+        TreeGraph::new(nodes.into_iter().collect(), HashMap::new())
+    }
+
+    fn lethal_pride() -> TimelessTables {
+        let (tables, errors) = TimelessTables::load_dir(FIXTURES);
+        assert!(errors.is_empty(), "{errors:?}");
+        tables
+    }
+
+    #[test]
+    fn loads_only_the_tables_present() {
+        let tables = lethal_pride();
+        assert!(tables.get(TimelessKind::LethalPride).is_some());
+        assert!(tables.get(TimelessKind::GloriousVanity).is_none());
+    }
+
+    #[test]
+    fn replaces_passives_by_seed_and_keystones_by_conqueror() {
+        let tables = lethal_pride();
+        let table = tables.get(TimelessKind::LethalPride).unwrap();
+        let graph = graph();
+        let at = |seed, conqueror, skill| {
+            let node = graph.node(skill).unwrap();
+            table
+                .replacement(seed, conqueror, node)
+                .map(|replacement| replacement.name.as_str())
+        };
+        assert_eq!(at(10000, "Kaom", 2), Some("Kaom's Spirit"));
+        assert_eq!(at(10002, "Kaom", 1), None);
+        assert_eq!(at(10000, "Kaom", 3), None);
+        assert_eq!(at(12345, "Kaom", 1), None);
+        assert_eq!(at(10000, "Kaom", 4), Some("Strength of Blood"));
+        assert_eq!(at(12345, "Akoya", 4), Some("Chainbreaker"));
+        assert_eq!(at(10000, "Rakiok", 4), None);
+    }
+
+    #[test]
+    fn seed_mod_round_trips() {
+        for kind in TimelessKind::ALL {
+            let seed = TimelessSeed {
+                kind,
+                seed: kind.seeds().1,
+                conqueror: kind.conquerors()[2].to_string(),
+            };
+            let mods = vec!["+10 to Strength".to_string(), seed.mod_line()];
+            assert_eq!(TimelessSeed::from_mods(&mods), Some(seed));
+        }
+        assert_eq!(
+            TimelessSeed::from_mods(
+                &["Commanded leadership over 10500 warriors under Kaom".into()]
+            ),
+            Some(TimelessSeed {
+                kind: TimelessKind::LethalPride,
+                seed: 10500,
+                conqueror: "Kaom".to_string(),
+            })
+        );
+        assert!(TimelessSeed::from_mods(&["+10 to Strength".to_string()]).is_none());
+    }
+
+    #[test]
+    fn apply_to_replaces_the_seed_mod() {
+        let mut jewel = Jewel::new(crate::jewel::JewelBase::Timeless);
+        jewel.mods = vec!["Commanded leadership over 10000 warriors under Kaom".to_string()];
+        let seed = TimelessSeed {
+            kind: TimelessKind::LethalPride,
+            seed: 10001,
+            conqueror: "Akoya".to_string(),
+        };
+        seed.apply_to(&mut jewel);
+        assert_eq!(jewel.mods, [seed.mod_line()]);
+    }
+
+    #[test]
+    fn snaps_seeds_to_what_the_jewel_rolls() {
+        let hubris = TimelessKind::ElegantHubris;
+        assert_eq!(hubris.snap_seed(2000), 2000);
+        assert_eq!(hubris.snap_seed(2009), 2000);
+        assert_eq!(hubris.snap_seed(2010), 2020);
+        assert_eq!(hubris.snap_seed(1), 2000);
+        assert_eq!(hubris.snap_seed(200000), 160000);
+        assert_eq!(TimelessKind::LethalPride.snap_seed(12345), 12345);
+        assert_eq!(TimelessKind::LethalPride.snap_seed(9000), 10000);
+    }
+
+    #[test]
+    fn search_ranks_seeds_by_matches() {
+        let tables = lethal_pride();
+        let table = tables.get(TimelessKind::LethalPride).unwrap();
+        let graph = graph();
+        let nodes: Vec<NodeIndex> = [1, 2, 3].map(|skill| graph.index_of(skill).unwrap()).into();
+        let search = |wanted: &[&str]| {
+            let wanted: Vec<String> = wanted.iter().map(|stat| stat.to_string()).collect();
+            search_seeds(table, &graph, &nodes, "Kaom", &wanted)
+                .into_iter()
+                .map(|found| (found.seed, found.counts))
+                .collect::<Vec<_>>()
+        };
+
+        // Ties go to the lower seed, seeds missing a stat are left out.
+        assert_eq!(
+            search(&["ATTACK SPEED", "strength"]),
+            [(10001, vec![2, 2]), (10000, vec![1, 1])]
+        );
+        assert_eq!(
+            search(&["critical strike"]),
+            [(10003, vec![2]), (10000, vec![1])]
+        );
+        assert!(search(&["cast speed"]).is_empty());
+        assert!(search(&[]).is_empty());
+    }
+}
@@ -1,9 +1,18 @@
-use std::{collections::HashMap, f32::consts::PI, sync::OnceLock};
+use std::{collections::HashMap, f32::consts::PI, fmt, sync::OnceLock};
 
 use egui::{pos2, Pos2};
 use serde::{Deserialize, Serialize};
 
-const TREE_DATA: &[u8; 5996378] = include_bytes!("../resources/data.json");
+use crate::graph::{NodeIndex, TreeGraph};
+
+/// Where the native build looks for a `skilltree-export` data.json when no path is given.
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "embed-tree")))]
+const DEFAULT_TREE_PATH: &str = "resources/data.json";
+/// Where the web build fetches its data.json from, relative to the page. Trunk copies it there.
+#[cfg(all(target_arch = "wasm32", not(feature = "embed-tree")))]
+const DEFAULT_TREE_URL: &str = "data.json";
+#[cfg(feature = "embed-tree")]
+const EMBEDDED_TREE: &[u8] = include_bytes!("../resources/data.json");
 pub const ORBIT_ANGLES_16: [i32; 16] = [
     0, 30, 45, 60, 90, 120, 135, 150, 180, 210, 225, 240, 270, 300, 315, 330,
 ];
@@ -25,8 +34,24 @@ pub const CLASS_ART: [&str; 8] = [
     "PSStartNodeBackgroundInactive",
 ];
 
-#[derive(Serialize, Deserialize, Default)]
-pub struct Class {}
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Ascendancy {
+    pub id: String,
+    pub name: String,
+    pub flavour_text: Option<String>,
+}
+
+/// A character class, in the same position in `TreeExport::classes` as its class id.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Class {
+    pub name: String,
+    pub base_str: u32,
+    pub base_dex: u32,
+    pub base_int: u32,
+    #[serde(default)]
+    pub ascendancies: Vec<Ascendancy>,
+}
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -62,26 +87,62 @@ pub struct Sprite {
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct Spritesheet {
-    #[serde(rename = "0.3835")]
+    #[serde(rename = "0.3835", default)]
     pub sprites: Sprite,
-    #[serde(rename = "0.2972")]
+    #[serde(rename = "0.2972", default)]
     pub worse_sprites: Sprite,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Sprites {
+    #[serde(default)]
+    pub background: Spritesheet,
     pub normal_active: Spritesheet,
+    #[serde(default)]
+    pub normal_inactive: Spritesheet,
     pub group_background: Spritesheet,
     pub frame: Spritesheet,
     pub mastery_inactive: Spritesheet,
+    #[serde(default)]
+    pub mastery_connected: Spritesheet,
+    #[serde(default)]
+    pub mastery_active_selected: Spritesheet,
     pub start_node: Spritesheet,
     pub notable_active: Spritesheet,
     pub keystone_active: Spritesheet,
     pub ascendancy_background: Spritesheet,
+    #[serde(default)]
+    pub jewel: Spritesheet,
+    #[serde(default)]
+    pub jewel_radius: Spritesheet,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MasteryEffect {
+    pub effect: usize,
+    #[serde(default)]
+    pub stats: Vec<String>,
+    #[serde(default)]
+    pub reminder_text: Vec<String>,
+}
+
+/// Marks a socket that cluster jewels can expand. `proxy` is the hidden node whose group and
+/// orbit the generated subtree is laid out on; sockets inside another socket's subtree name it
+/// as their `parent`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpansionJewel {
+    /// 0 for small, 1 for medium and 2 for large sockets.
+    pub size: usize,
+    pub index: usize,
+    pub proxy: String,
+    #[serde(default)]
+    pub parent: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Node {
     pub skill: Option<usize>,
@@ -97,13 +158,27 @@ pub struct Node {
     #[serde(default)]
     pub inactive_icon: String,
     #[serde(default)]
+    pub active_icon: String,
+    #[serde(default)]
+    pub mastery_effects: Vec<MasteryEffect>,
+    #[serde(default)]
     pub is_keystone: bool,
     #[serde(default)]
+    pub is_jewel_socket: bool,
+    #[serde(default)]
+    pub expansion_jewel: Option<ExpansionJewel>,
+    #[serde(default)]
     pub out: Vec<String>,
     pub class_start_index: Option<usize>,
     pub ascendancy_name: Option<String>,
     #[serde(default)]
     pub is_ascendancy_start: bool,
+    #[serde(default)]
+    pub stats: Vec<String>,
+    #[serde(default)]
+    pub reminder_text: Vec<String>,
+    #[serde(default)]
+    pub flavour_text: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -116,7 +191,9 @@ pub struct Constants {
 pub struct TreeExport {
     pub tree: String,
     pub classes: Vec<Class>,
+    /// Raw export data, moved into `graph` once loaded.
     pub groups: HashMap<String, Group>,
+    /// Raw export data, moved into `graph` once loaded.
     pub nodes: HashMap<String, Node>,
     pub min_x: f32,
     pub min_y: f32,
@@ -124,16 +201,30 @@ pub struct TreeExport {
     pub max_y: f32,
     pub sprites: Sprites,
     pub constants: Constants,
+    #[serde(skip)]
+    pub graph: TreeGraph,
+    /// The `assets` folder next to the data.json this was read from, as GGG lays out its export.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub asset_dir: Option<std::path::PathBuf>,
+}
+
+pub struct Line {
+    pub from: NodeIndex,
+    pub to: NodeIndex,
+    pub start: Pos2,
+    pub end: Pos2,
 }
 
 pub enum FrameType {
     Normal,
     Notable,
     Keystone,
+    Jewel,
     None,
 }
 
-fn orbit_position(node: &Node, group: &Group) -> (f32, f32) {
+pub fn orbit_position(node: &Node, group: &Group) -> (f32, f32) {
     let radius = ORBIT_RADII[node.orbit.unwrap_or(0)] as f32;
     let skills_on_orbit = ORBIT_NODES[node.orbit.unwrap_or(0)];
     let orbit_index = node.orbit_index.unwrap_or(0);
@@ -161,66 +252,136 @@ pub fn fix_export(tree: TreeExport) -> TreeExport {
         .collect::<Vec<&Node>>();
 
     for node in start_nodes {
-        let (group_id, group) = fixed_tree
-            .groups
-            .iter()
-            .find(|(id, _)| id.parse::<usize>().unwrap() == node.group.unwrap())
-            .unwrap();
-        let ascendancy_name = node.ascendancy_name.as_ref().unwrap();
-        let new_position = ascendancy_starts().get(ascendancy_name as &str).unwrap();
-        let new_group = Group {
-            background: group.background.clone(),
-            nodes: group.nodes.clone(),
-            orbits: group.orbits.clone(),
-            x: new_position.x as f32,
-            y: new_position.y as f32,
+        let Some(ascendancy_name) = node.ascendancy_name.as_deref() else {
+            continue;
+        };
+        // Ascendancies added after this table was written keep the export's own position.
+        let Some(new_position) = ascendancy_starts().get(ascendancy_name) else {
+            log::warn!("No layout for ascendancy {ascendancy_name:?}, keeping its export position");
+            continue;
         };
-        fixed_tree.groups.insert(group_id.to_string(), new_group);
+        if let Some(group) = node
+            .group
+            .and_then(|group| fixed_tree.groups.get_mut(&group.to_string()))
+        {
+            group.x = new_position.x;
+            group.y = new_position.y;
+        }
     }
 
     fixed_tree
 }
 
-impl TreeExport {
-    pub fn new() -> Option<TreeExport> {
-        match serde_json::from_str(&String::from_utf8_lossy(TREE_DATA)) {
-            Ok(te) => {
-                let fixed = fix_export(te);
-                Some(fixed)
-            }
-            Err(e) => panic!("{}", e),
+#[derive(Debug)]
+pub enum TreeError {
+    #[cfg(not(target_arch = "wasm32"))]
+    Io(std::path::PathBuf, std::io::Error),
+    #[cfg(target_arch = "wasm32")]
+    Fetch(String),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Io(path, e) => write!(f, "could not read tree data from {}: {e}", path.display()),
+            #[cfg(target_arch = "wasm32")]
+            Self::Fetch(e) => write!(f, "could not fetch tree data: {e}"),
+            Self::Parse(e) => write!(f, "tree data is not a valid skill tree export: {e}"),
         }
     }
+}
 
-    pub fn node_position(&self, node: &Node) -> (f32, f32) {
-        if let Some(group_num) = node.group {
-            let group_str = format!("{group_num}");
-            if let Some(group) = self.groups.get(&group_str) {
-                return orbit_position(node, group);
-            }
+impl std::error::Error for TreeError {}
+
+impl From<serde_json::Error> for TreeError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl TreeExport {
+    /// Parses the contents of a GGG `skilltree-export` data.json.
+    pub fn from_slice(data: &[u8]) -> Result<TreeExport, TreeError> {
+        let mut fixed = fix_export(serde_json::from_slice(data)?);
+        fixed.graph = TreeGraph::new(
+            std::mem::take(&mut fixed.nodes),
+            std::mem::take(&mut fixed.groups),
+        );
+        Ok(fixed)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<TreeExport, TreeError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| TreeError::Io(path.to_path_buf(), e))?;
+        let mut tree = Self::from_slice(&data)?;
+        tree.asset_dir = path.parent().map(|dir| dir.join("assets"));
+        Ok(tree)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub async fn fetch(url: &str) -> Result<TreeExport, TreeError> {
+        let response = ehttp::fetch_async(ehttp::Request::get(url))
+            .await
+            .map_err(TreeError::Fetch)?;
+        if !response.ok {
+            return Err(TreeError::Fetch(format!(
+                "{} {} {}",
+                url, response.status, response.status_text
+            )));
         }
-        (0.0, 0.0)
+        Self::from_slice(&response.bytes)
+    }
+
+    /// The tree compiled into the binary with the `embed-tree` feature, or the default data.json
+    /// on disk otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_default() -> Result<TreeExport, TreeError> {
+        #[cfg(feature = "embed-tree")]
+        return Self::from_slice(EMBEDDED_TREE);
+        #[cfg(not(feature = "embed-tree"))]
+        Self::from_file(DEFAULT_TREE_PATH)
+    }
+
+    /// The tree compiled into the binary with the `embed-tree` feature, or the bundled data.json
+    /// fetched next to the page otherwise.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn load_default() -> Result<TreeExport, TreeError> {
+        #[cfg(feature = "embed-tree")]
+        return Self::from_slice(EMBEDDED_TREE);
+        #[cfg(not(feature = "embed-tree"))]
+        Self::fetch(DEFAULT_TREE_URL).await
+    }
+
+    /// Ascendancies of the class with id `class`, in the order the game numbers them from 1.
+    pub fn ascendancies(&self, class: usize) -> &[Ascendancy] {
+        self.classes
+            .get(class)
+            .map_or(&[], |class| class.ascendancies.as_slice())
     }
 
-    pub fn generate_lines(&self) -> Vec<(Pos2, Pos2)> {
+    /// The ascendancy a build's `ascendancy` number picks, `None` when it's 0 or out of range.
+    pub fn ascendancy(&self, class: usize, ascendancy: u8) -> Option<&Ascendancy> {
+        let index = (ascendancy as usize).checked_sub(1)?;
+        self.ascendancies(class).get(index)
+    }
+
+    pub fn generate_lines(&self) -> Vec<Line> {
+        let graph = &self.graph;
         let mut lines = vec![];
-        for node in self.nodes.values() {
-            let node_pos = self.node_position(node);
-            let out_nodes: Vec<&Node> = node
-                .out
-                .iter()
-                .map(|x| self.nodes.get(x).unwrap())
-                .collect();
-            for other in out_nodes {
-                if !node.is_mastery
-                    && !other.is_mastery
-                    && !node.is_proxy.unwrap_or(false)
-                    && !other.is_proxy.unwrap_or(false)
-                    && node.class_start_index == other.class_start_index
-                    && node.ascendancy_name == other.ascendancy_name
+        for (from, neighbours) in graph.adjacency.iter().enumerate() {
+            for &to in neighbours.iter().filter(|&&to| from < to) {
+                if graph.nodes[from].class_start_index.is_none()
+                    && graph.nodes[to].class_start_index.is_none()
                 {
-                    let other_pos = self.node_position(other);
-                    lines.push((pos2(node_pos.0, node_pos.1), pos2(other_pos.0, other_pos.1)));
+                    lines.push(Line {
+                        from,
+                        to,
+                        start: graph.positions[from],
+                        end: graph.positions[to],
+                    });
                 }
             }
         }
@@ -229,20 +390,22 @@ impl TreeExport {
     }
 
     pub fn get_ascendancy_starts(&self) -> Vec<(Pos2, String)> {
-        let start_nodes = self
+        let graph = &self.graph;
+        graph
             .nodes
-            .values()
-            .filter(|x| x.is_ascendancy_start)
-            .collect::<Vec<&Node>>();
-        start_nodes
-            .into_iter()
-            .map(|node| {
-                let ascendancy_name = node.ascendancy_name.as_ref().unwrap();
-                let group = ascendancy_starts().get(ascendancy_name as &str).unwrap();
-                (
-                    pos2(group.x, group.y),
-                    format!("Classes{}", &node.ascendancy_name.as_ref().unwrap()),
-                )
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_ascendancy_start)
+            .filter_map(|(index, node)| {
+                let ascendancy_name = node.ascendancy_name.as_deref()?;
+                let position = match ascendancy_starts().get(ascendancy_name) {
+                    Some(position) => *position,
+                    None => {
+                        let group = &graph.groups[graph.group_of(index)?];
+                        pos2(group.x, group.y)
+                    }
+                };
+                Some((position, format!("Classes{ascendancy_name}")))
             })
             .collect()
     }
@@ -250,6 +413,10 @@ impl TreeExport {
 
 impl Node {
     pub fn frame_type(&self) -> FrameType {
+        if self.is_jewel_socket {
+            return FrameType::Jewel;
+        }
+
         if self.is_notable.unwrap_or(false) {
             return FrameType::Notable;
         }
@@ -264,6 +431,37 @@ impl Node {
 
         FrameType::None
     }
+
+    /// How the game labels the node in its tooltip.
+    pub fn kind(&self) -> &'static str {
+        if self.is_mastery {
+            return "Mastery";
+        }
+        match self.frame_type() {
+            FrameType::Keystone => "Keystone",
+            FrameType::Notable => "Notable",
+            FrameType::Jewel => "Jewel Socket",
+            _ => "Passive",
+        }
+    }
+
+    /// Masteries and proxies never take part in paths, and ascendancy trees are islands.
+    pub fn can_connect(&self, other: &Node) -> bool {
+        !self.is_mastery
+            && !other.is_mastery
+            && !self.is_proxy.unwrap_or(false)
+            && !other.is_proxy.unwrap_or(false)
+            && self.ascendancy_name == other.ascendancy_name
+    }
+
+    /// Class starts, ascendancy starts, masteries and proxies can't be clicked on directly.
+    pub fn is_allocatable(&self) -> bool {
+        self.skill.is_some()
+            && self.class_start_index.is_none()
+            && !self.is_ascendancy_start
+            && !self.is_mastery
+            && !self.is_proxy.unwrap_or(false)
+    }
 }
 
 // This acts like the `lazy_static` crate and allows for e.g. static HashMaps; It only gets computed once when first called.
@@ -307,3 +505,65 @@ pub fn ascendancy_starts() -> &'static HashMap<&'static str, Pos2> {
         m
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEETS: &str = r#""normalActive":{},"groupBackground":{},"frame":{},"masteryInactive":{},
+        "startNode":{},"notableActive":{},"keystoneActive":{},"ascendancyBackground":{}"#;
+
+    fn export(ascendancy: &str) -> String {
+        format!(
+            r#"{{"tree":"test","classes":[],"min_x":0,"min_y":0,"max_x":0,"max_y":0,
+            "constants":{{"orbitRadii":[]}},"sprites":{{{SHEETS}}},
+            "groups":{{"1":{{"x":5.0,"y":7.0,"orbits":[0],"nodes":["10"]}}}},
+            "nodes":{{"10":{{"skill":10,"group":1,"orbit":0,"orbitIndex":0,
+                "isAscendancyStart":true,"ascendancyName":"{ascendancy}"}}}}}}"#
+        )
+    }
+
+    #[test]
+    fn moves_known_ascendancies() {
+        let tree = TreeExport::from_slice(export("Juggernaut").as_bytes()).unwrap();
+        assert_eq!(
+            tree.get_ascendancy_starts(),
+            [(pos2(-10400.0, 5200.0), "ClassesJuggernaut".to_string())]
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_ascendancies_in_place() {
+        let tree = TreeExport::from_slice(export("Reliquarian").as_bytes()).unwrap();
+        assert_eq!(
+            tree.get_ascendancy_starts(),
+            [(pos2(5.0, 7.0), "ClassesReliquarian".to_string())]
+        );
+    }
+
+    #[test]
+    fn numbers_ascendancies_from_one() {
+        let ascendancy = |id: &str| Ascendancy {
+            id: id.to_string(),
+            name: id.to_string(),
+            flavour_text: None,
+        };
+        let tree = TreeExport {
+            classes: vec![
+                Class {
+                    name: "Scion".to_string(),
+                    ascendancies: vec![ascendancy("Ascendant"), ascendancy("Reliquarian")],
+                    ..Default::default()
+                },
+                Class::default(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(tree.ascendancies(0).len(), 2);
+        assert!(tree.ascendancy(0, 0).is_none());
+        assert_eq!(tree.ascendancy(0, 2).unwrap().id, "Reliquarian");
+        assert!(tree.ascendancy(0, 3).is_none());
+        assert!(tree.ascendancy(1, 1).is_none());
+        assert!(tree.ascendancies(7).is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+
+use crate::tree::TreeExport;
+
+pub const URL_PREFIX: &str = "https://www.pathofexile.com/passive-skill-tree/";
+const VERSION: u32 = 6;
+const CLASS_COUNT: u8 = 7;
+/// The ascendancy takes the low bits of its byte, the ones above pick an alternate ascendancy.
+const ASCENDANCY_MASK: u8 = 0b11;
+
+// The game pads its links, most copy-pasted ones aren't; accept both.
+const ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Everything the game's passive tree link carries.
+#[derive(Debug, Default, PartialEq)]
+pub struct TreeUrl {
+    pub class: u8,
+    pub ascendancy: u8,
+    pub nodes: Vec<u16>,
+    pub cluster_nodes: Vec<u16>,
+    /// `(mastery node, effect)` pairs.
+    pub mastery_effects: Vec<(u16, u16)>,
+}
+
+impl TreeUrl {
+    /// Checks the ascendancy against the classes `tree` lists, which the link alone can't.
+    pub fn check_ascendancy(&self, tree: &TreeExport) -> Result<(), TreeUrlError> {
+        if self.ascendancy == 0
+            || tree
+                .ascendancy(self.class as usize, self.ascendancy)
+                .is_some()
+        {
+            Ok(())
+        } else {
+            Err(TreeUrlError::InvalidAscendancy(self.ascendancy))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TreeUrlError {
+    Base64(base64::DecodeError),
+    UnexpectedEnd,
+    UnsupportedVersion(u32),
+    InvalidClass(u8),
+    InvalidAscendancy(u8),
+    TooManyEntries(&'static str),
+}
+
+impl fmt::Display for TreeUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base64(e) => write!(f, "tree link is not valid base64: {e}"),
+            Self::UnexpectedEnd => write!(f, "tree link ends unexpectedly"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported tree link version {v}"),
+            Self::InvalidClass(c) => write!(f, "unknown class id {c}"),
+            Self::InvalidAscendancy(a) => write!(f, "unknown ascendancy id {a}"),
+            Self::TooManyEntries(what) => write!(f, "too many {what} to fit in a tree link"),
+        }
+    }
+}
+
+impl From<base64::DecodeError> for TreeUrlError {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Base64(e)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Result<u8, TreeUrlError> {
+        let (&first, rest) = self
+            .bytes
+            .split_first()
+            .ok_or(TreeUrlError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(first)
+    }
+
+    fn u16(&mut self) -> Result<u16, TreeUrlError> {
+        Ok(u16::from_be_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> Result<u32, TreeUrlError> {
+        Ok(u32::from_be_bytes([
+            self.u8()?,
+            self.u8()?,
+            self.u8()?,
+            self.u8()?,
+        ]))
+    }
+
+    fn u16_list(&mut self, count: usize) -> Result<Vec<u16>, TreeUrlError> {
+        (0..count).map(|_| self.u16()).collect()
+    }
+}
+
+/// Accepts a full link, a link with a tree version segment, or just the encoded blob.
+pub fn decode(input: &str) -> Result<TreeUrl, TreeUrlError> {
+    let input = input.trim();
+    let input = input.split(['?', '#']).next().unwrap_or_default();
+    let blob = input
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let bytes = ENGINE.decode(blob)?;
+    let mut reader = Reader { bytes: &bytes };
+
+    let version = reader.u32()?;
+    if !(4..=VERSION).contains(&version) {
+        return Err(TreeUrlError::UnsupportedVersion(version));
+    }
+    let class = reader.u8()?;
+    if class >= CLASS_COUNT {
+        return Err(TreeUrlError::InvalidClass(class));
+    }
+    // Alternate ascendancies aren't supported, only the class's own is kept.
+    let ascendancy = reader.u8()? & ASCENDANCY_MASK;
+
+    let mut url = TreeUrl {
+        class,
+        ascendancy,
+        ..Default::default()
+    };
+    if version == 4 {
+        // Version 4 stores a "fullscreen" flag here and runs nodes to the end of the data.
+        reader.u8()?;
+        let count = reader.bytes.len() / 2;
+        url.nodes = reader.u16_list(count)?;
+        return Ok(url);
+    }
+
+    let count = reader.u8()? as usize;
+    url.nodes = reader.u16_list(count)?;
+    let count = reader.u8()? as usize;
+    url.cluster_nodes = reader.u16_list(count)?;
+    if version >= 6 {
+        let count = reader.u8()? as usize;
+        for _ in 0..count {
+            let effect = reader.u16()?;
+            let node = reader.u16()?;
+            url.mastery_effects.push((node, effect));
+        }
+    }
+
+    Ok(url)
+}
+
+/// Produces a full link in the current (version 6) format.
+pub fn encode(url: &TreeUrl) -> Result<String, TreeUrlError> {
+    let mut bytes = VERSION.to_be_bytes().to_vec();
+    bytes.push(url.class);
+    bytes.push(url.ascendancy);
+
+    for (what, list) in [("nodes", &url.nodes), ("cluster nodes", &url.cluster_nodes)] {
+        let count = u8::try_from(list.len()).map_err(|_| TreeUrlError::TooManyEntries(what))?;
+        bytes.push(count);
+        bytes.extend(list.iter().flat_map(|node| node.to_be_bytes()));
+    }
+
+    let count = u8::try_from(url.mastery_effects.len())
+        .map_err(|_| TreeUrlError::TooManyEntries("mastery effects"))?;
+    bytes.push(count);
+    for (node, effect) in &url.mastery_effects {
+        bytes.extend(effect.to_be_bytes());
+        bytes.extend(node.to_be_bytes());
+    }
+
+    Ok(format!("{URL_PREFIX}{}", ENGINE.encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Ascendancy, Class};
+
+    fn link(bytes: &[u8]) -> String {
+        format!("{URL_PREFIX}{}", ENGINE.encode(bytes))
+    }
+
+    fn sample() -> TreeUrl {
+        TreeUrl {
+            class: 3,
+            ascendancy: 2,
+            nodes: vec![4367, 21984, 54447],
+            cluster_nodes: vec![32, 38],
+            mastery_effects: vec![(59920, 47642)],
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let url = sample();
+        let encoded = encode(&url).unwrap();
+        assert!(encoded.starts_with(URL_PREFIX));
+        assert_eq!(decode(&encoded).unwrap(), url);
+
+        // Versioned links, trailing query strings and bare blobs all read the same.
+        let blob = encoded.strip_prefix(URL_PREFIX).unwrap();
+        assert_eq!(decode(&format!("{URL_PREFIX}3.25.0/{blob}")).unwrap(), url);
+        assert_eq!(decode(&format!("{encoded}?accountName=x")).unwrap(), url);
+        assert_eq!(
+            decode(&format!(" {} ", blob.trim_end_matches('='))).unwrap(),
+            url
+        );
+    }
+
+    #[test]
+    fn reads_every_version() {
+        let v4 = [0, 0, 0, 4, 1, 1, 0, 0x11, 0x0f, 0xd5, 0xe0];
+        assert_eq!(
+            decode(&link(&v4)).unwrap(),
+            TreeUrl {
+                class: 1,
+                ascendancy: 1,
+                nodes: vec![4367, 54752],
+                ..Default::default()
+            }
+        );
+
+        let v5 = [0, 0, 0, 5, 2, 0, 1, 0x11, 0x0f, 1, 0, 0x20];
+        assert_eq!(
+            decode(&link(&v5)).unwrap(),
+            TreeUrl {
+                class: 2,
+                nodes: vec![4367],
+                cluster_nodes: vec![32],
+                ..Default::default()
+            }
+        );
+
+        // Version 6 writes the effect before its mastery.
+        let v6 = [0, 0, 0, 6, 6, 3, 0, 0, 1, 0xba, 0x1a, 0xea, 0x10];
+        assert_eq!(
+            decode(&link(&v6)).unwrap(),
+            TreeUrl {
+                class: 6,
+                ascendancy: 3,
+                mastery_effects: vec![(59920, 47642)],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_alternate_ascendancy_bits() {
+        let bytes = [0, 0, 0, 6, 1, 0b0100_0001, 0, 0, 0];
+        assert_eq!(decode(&link(&bytes)).unwrap().ascendancy, 1);
+    }
+
+    #[test]
+    fn rejects_bad_links() {
+        assert!(matches!(
+            decode("not a tree!"),
+            Err(TreeUrlError::Base64(_))
+        ));
+        assert!(matches!(
+            decode(&link(&[0, 0, 0])),
+            Err(TreeUrlError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            decode(&link(&[0, 0, 0, 6, 1, 0, 2, 0x11, 0x0f])),
+            Err(TreeUrlError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            decode(&link(&[0, 0, 0, 3, 1, 0, 0])),
+            Err(TreeUrlError::UnsupportedVersion(3))
+        ));
+        assert!(matches!(
+            decode(&link(&[0, 0, 0, 7, 1, 0, 0, 0, 0])),
+            Err(TreeUrlError::UnsupportedVersion(7))
+        ));
+        assert!(matches!(
+            decode(&link(&[0, 0, 0, 6, 7, 0, 0, 0, 0])),
+            Err(TreeUrlError::InvalidClass(7))
+        ));
+
+        let url = TreeUrl {
+            nodes: vec![1; 256],
+            ..Default::default()
+        };
+        assert!(matches!(
+            encode(&url),
+            Err(TreeUrlError::TooManyEntries("nodes"))
+        ));
+    }
+
+    #[test]
+    fn checks_ascendancy_against_the_tree() {
+        let class = |name: &str, ascendancies: &[&str]| Class {
+            name: name.to_string(),
+            ascendancies: ascendancies
+                .iter()
+                .map(|&id| Ascendancy {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    flavour_text: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let tree = TreeExport {
+            classes: vec![
+                class("Scion", &["Ascendant"]),
+                class("Marauder", &["Juggernaut", "Berserker", "Chieftain"]),
+            ],
+            ..Default::default()
+        };
+        let url = |class, ascendancy| TreeUrl {
+            class,
+            ascendancy,
+            ..Default::default()
+        };
+        assert!(url(0, 0).check_ascendancy(&tree).is_ok());
+        assert!(url(0, 1).check_ascendancy(&tree).is_ok());
+        assert!(matches!(
+            url(0, 2).check_ascendancy(&tree),
+            Err(TreeUrlError::InvalidAscendancy(2))
+        ));
+        assert!(url(1, 3).check_ascendancy(&tree).is_ok());
+    }
+}